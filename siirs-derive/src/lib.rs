@@ -0,0 +1,447 @@
+//! `#[derive(FromStruct)]` for decoding sii `Struct`s into plain Rust structs.
+//!
+//! Container attributes:
+//!
+//!   * `#[sii(struct_name = "economy")]` -- fail unless the block is a
+//!     `struct_name` block.
+//!   * `#[sii(singleton)]` -- also implement `FromGameSave` by finding the one
+//!     block named `struct_name` in the save.
+//!
+//! Field attributes:
+//!
+//!   * `#[sii(id)]` -- the block's own ID.
+//!   * `#[sii(rename = "long_dist")]` -- read from a differently named field.
+//!   * `#[sii(optional)]` -- the field may be missing; the Rust type must be
+//!     an `Option`.
+//!   * `#[sii(default)]` -- the field may be missing; uses `Default` if so.
+//!   * `#[sii(resolve)]` -- the field is a reference (`T`, `Option<T>`) or an
+//!     array of references (`Vec<T>`) to other blocks, which are decoded as
+//!     `T: FromStruct`.  Requires a `GameSave`, so types with resolved fields
+//!     do not get `TryFrom<Struct>` implementations.
+//!   * `#[sii(flatten)]` -- decode another `FromStruct` type from the same
+//!     block.  If that type requires a `GameSave`, so does this one.
+//!   * `#[sii(with = "path::to::fn")]` -- decode with `fn(&Struct) ->
+//!     Result<T>`.
+//!   * `#[sii(with_save = "path::to::fn")]` -- decode with `fn(&Struct,
+//!     &GameSave) -> Result<T>`, e.g. to look at what refers to the block.
+//!     Like `resolve`, this requires a `GameSave`.
+//!
+//! Everything else is read with `Struct::get_as`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields, GenericArgument,
+    LitStr, Path, PathArguments, Type,
+};
+
+#[proc_macro_derive(FromStruct, attributes(sii))]
+pub fn derive_from_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ContainerAttrs {
+    struct_name: Option<LitStr>,
+    singleton: bool,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    id: bool,
+    rename: Option<LitStr>,
+    optional: bool,
+    default: bool,
+    resolve: bool,
+    flatten: bool,
+    with: Option<Path>,
+    with_save: Option<Path>,
+}
+
+fn parse_container_attrs(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut out = ContainerAttrs::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("sii")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("struct_name") {
+                out.struct_name = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("singleton") {
+                out.singleton = true;
+            } else {
+                return Err(meta.error("unknown sii container attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    if out.singleton && out.struct_name.is_none() {
+        return Err(Error::new(
+            input.ident.span(),
+            "#[sii(singleton)] requires #[sii(struct_name = \"...\")]",
+        ));
+    }
+
+    Ok(out)
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut out = FieldAttrs::default();
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("sii")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                out.id = true;
+            } else if meta.path.is_ident("rename") {
+                out.rename = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("optional") {
+                out.optional = true;
+            } else if meta.path.is_ident("default") {
+                out.default = true;
+            } else if meta.path.is_ident("resolve") {
+                out.resolve = true;
+            } else if meta.path.is_ident("flatten") {
+                out.flatten = true;
+            } else if meta.path.is_ident("with") {
+                let path: LitStr = meta.value()?.parse()?;
+                out.with = Some(path.parse()?);
+            } else if meta.path.is_ident("with_save") {
+                let path: LitStr = meta.value()?.parse()?;
+                out.with_save = Some(path.parse()?);
+            } else {
+                return Err(meta.error("unknown sii field attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    let exclusive = [
+        out.id,
+        out.resolve,
+        out.flatten,
+        out.with.is_some(),
+        out.with_save.is_some(),
+    ];
+    if exclusive.iter().filter(|x| **x).count() > 1 {
+        return Err(Error::new(
+            field.span(),
+            "id, resolve, flatten, with and with_save cannot be combined",
+        ));
+    }
+    if out.optional && out.default {
+        return Err(Error::new(field.span(), "optional and default cannot be combined"));
+    }
+
+    Ok(out)
+}
+
+/// If `ty` is `Wrapper<T>` (by last path segment), returns `T`.
+fn unwrap_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let Type::Path(p) = ty else { return None };
+    let segment = p.path.segments.last()?;
+    if segment.ident != wrapper {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(GenericArgument::Type(t)) if args.args.len() == 1 => Some(t),
+        _ => None,
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attrs = parse_container_attrs(&input)?;
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "FromStruct cannot be derived for generic types",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => return Err(Error::new(input.span(), "FromStruct requires named fields")),
+        },
+        _ => return Err(Error::new(input.span(), "FromStruct can only be derived for structs")),
+    };
+
+    let krate = quote!(::siirs::sii::game::__derive);
+    let mut needs_save = false;
+    let mut flattened = Vec::new();
+    let mut initializers = Vec::new();
+
+    for field in fields {
+        let field_attrs = parse_field_attrs(field)?;
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let name = field_attrs
+            .rename
+            .clone()
+            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+
+        let require_save = quote! {
+            save.ok_or_else(|| #krate::anyhow!("decoding {} requires a GameSave", #name))?
+        };
+        let expr = if field_attrs.id {
+            quote!(value.id.clone())
+        } else if field_attrs.flatten {
+            flattened.push(ty);
+            quote!(<#ty as #krate::FromStruct>::from_struct(value, save)?)
+        } else if let Some(with) = &field_attrs.with {
+            quote!(#with(value)?)
+        } else if let Some(with_save) = &field_attrs.with_save {
+            needs_save = true;
+            quote!(#with_save(value, #require_save)?)
+        } else if field_attrs.resolve {
+            needs_save = true;
+            let save = &require_save;
+            let resolved = if let Some(inner) = unwrap_type(ty, "Vec") {
+                quote! {
+                    value
+                        .get_as::<::std::vec::Vec<#krate::ID>>(#name)?
+                        .iter()
+                        .map(|id| #krate::resolve_ref::<#inner>(#save, id))
+                        .collect::<#krate::Result<#ty>>()?
+                }
+            } else if let Some(inner) = unwrap_type(ty, "Option") {
+                quote! {
+                    value
+                        .get_as::<::std::option::Option<#krate::ID>>(#name)?
+                        .map(|id| #krate::resolve_ref::<#inner>(#save, &id))
+                        .transpose()?
+                }
+            } else {
+                quote!(#krate::resolve_ref::<#ty>(#save, &value.get_as::<#krate::ID>(#name)?)?)
+            };
+
+            if field_attrs.optional || field_attrs.default {
+                return Err(Error::new(
+                    field.span(),
+                    "resolved fields cannot be optional; use an Option<T> reference instead",
+                ));
+            }
+            resolved
+        } else if field_attrs.optional {
+            let inner = unwrap_type(ty, "Option").ok_or_else(|| {
+                Error::new(ty.span(), "#[sii(optional)] fields must be an Option")
+            })?;
+            quote!(value.get_opt_as::<#inner>(#name)?)
+        } else if field_attrs.default {
+            quote!(value.get_opt_as::<#ty>(#name)?.unwrap_or_default())
+        } else {
+            quote!(value.get_as::<#ty>(#name)?)
+        };
+
+        initializers.push(quote!(#ident: #expr));
+    }
+
+    let ident = &input.ident;
+    let struct_name = match &attrs.struct_name {
+        Some(n) => quote!(::std::option::Option::Some(#n)),
+        None => quote!(::std::option::Option::None),
+    };
+
+    let mut out = quote! {
+        impl #krate::FromStruct for #ident {
+            const STRUCT_NAME: ::std::option::Option<&'static str> = #struct_name;
+
+            #[allow(unused_variables)]
+            fn from_struct(
+                value: &#krate::Struct,
+                save: ::std::option::Option<&#krate::GameSave>,
+            ) -> #krate::Result<Self> {
+                if let ::std::option::Option::Some(name) = Self::STRUCT_NAME {
+                    #krate::expect_struct_name(value, name)?;
+                }
+
+                ::std::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    };
+
+    if !needs_save {
+        // Whether flattened types need a save is only known once they are
+        // compiled.  The `for<'x>` keeps the bound from being checked when
+        // the impls are declared, so an unmet one leaves them unusable rather
+        // than failing the build.
+        let bounds = if flattened.is_empty() {
+            quote!()
+        } else {
+            quote!(where #(for<'x> #flattened: #krate::WithoutSave,)*)
+        };
+        out.extend(quote! {
+            impl #krate::WithoutSave for #ident #bounds {}
+
+            impl ::std::convert::TryFrom<&#krate::Struct> for #ident #bounds {
+                type Error = #krate::Error;
+
+                fn try_from(value: &#krate::Struct) -> #krate::Result<Self> {
+                    <Self as #krate::FromStruct>::from_struct(value, ::std::option::Option::None)
+                }
+            }
+
+            impl ::std::convert::TryFrom<#krate::Struct> for #ident #bounds {
+                type Error = #krate::Error;
+
+                fn try_from(value: #krate::Struct) -> #krate::Result<Self> {
+                    <Self as #krate::FromStruct>::from_struct(&value, ::std::option::Option::None)
+                }
+            }
+        });
+    }
+
+    if let (true, Some(name)) = (attrs.singleton, &attrs.struct_name) {
+        out.extend(quote! {
+            impl #krate::FromGameSave for #ident {
+                fn from_game_save(save: &#krate::GameSave) -> #krate::Result<Self> {
+                    let name = #name;
+                    let block = save
+                        .single_block_named(name)
+                        .ok_or_else(|| #krate::anyhow!("missing {} data", name))?;
+                    <Self as #krate::FromStruct>::from_struct(block, ::std::option::Option::Some(save))
+                }
+            }
+        });
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, DeriveInput};
+
+    use super::expand;
+
+    // The expansion without whitespace, so fragments can be matched as written.
+    fn expanded(input: DeriveInput) -> String {
+        let out = expand(input).unwrap_or_else(|e| panic!("{e}")).to_string();
+        out.split_whitespace().collect()
+    }
+
+    fn error(input: DeriveInput) -> String {
+        match expand(input) {
+            Ok(out) => panic!("expected an error, got {out}"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn container_attributes() {
+        let out = expanded(parse_quote! {
+            #[sii(struct_name = "economy", singleton)]
+            struct Economy { game_time: u32 }
+        });
+        assert!(out.contains(r#"constSTRUCT_NAME:::std::option::Option<&'staticstr>=::std::option::Option::Some("economy");"#));
+        assert!(out.contains("impl::siirs::sii::game::__derive::FromGameSaveforEconomy"));
+        assert!(out.contains(r#"single_block_named(name)"#));
+
+        let out = expanded(parse_quote!(struct Plain { game_time: u32 }));
+        assert!(out.contains("::std::option::Option::None;"));
+        assert!(!out.contains("FromGameSave"));
+
+        assert!(error(parse_quote! {
+            #[sii(singleton)]
+            struct Economy { game_time: u32 }
+        })
+        .contains("requires #[sii(struct_name"));
+    }
+
+    #[test]
+    fn field_attributes() {
+        let out = expanded(parse_quote! {
+            struct Driver {
+                #[sii(id)]
+                id: ID,
+                #[sii(rename = "long_dist")]
+                long_distance: u32,
+                #[sii(optional)]
+                hometown: Option<String>,
+                #[sii(default)]
+                trucks: Vec<ID>,
+                #[sii(with = "skills::decode")]
+                skills: Skills,
+                adr: u8,
+            }
+        });
+        assert!(out.contains("id:value.id.clone()"));
+        assert!(out.contains(r#"long_distance:value.get_as::<u32>("long_dist")?"#));
+        assert!(out.contains(r#"hometown:value.get_opt_as::<String>("hometown")?"#));
+        assert!(out.contains(r#"trucks:value.get_opt_as::<Vec<ID>>("trucks")?.unwrap_or_default()"#));
+        assert!(out.contains("skills:skills::decode(value)?"));
+        assert!(out.contains(r#"adr:value.get_as::<u8>("adr")?"#));
+        assert!(out.contains("impl::std::convert::TryFrom<&::siirs::sii::game::__derive::Struct>forDriver{"));
+
+        assert!(error(parse_quote!(struct S { #[sii(optional)] a: u32 })).contains("must be an Option"));
+        assert!(error(parse_quote!(struct S { #[sii(optional, default)] a: Option<u32> }))
+            .contains("cannot be combined"));
+        assert!(error(parse_quote!(struct S { #[sii(id, flatten)] a: ID })).contains("cannot be combined"));
+    }
+
+    #[test]
+    fn save_attributes_drop_try_from() {
+        let out = expanded(parse_quote! {
+            struct Truck {
+                #[sii(resolve)]
+                accessories: Vec<Accessory>,
+                #[sii(resolve)]
+                trailer: Option<Trailer>,
+                #[sii(resolve)]
+                engine: Engine,
+            }
+        });
+        assert!(out.contains("resolve_ref::<Accessory>("));
+        assert!(out.contains("resolve_ref::<Trailer>("));
+        assert!(out.contains("resolve_ref::<Engine>("));
+        assert!(!out.contains("TryFrom"));
+        assert!(!out.contains("WithoutSave"));
+
+        let out = expanded(parse_quote! {
+            struct Truck {
+                #[sii(with_save = "owning_garage")]
+                garage: ID,
+            }
+        });
+        assert!(out.contains("owning_garage(value,save.ok_or_else("));
+        assert!(!out.contains("TryFrom"));
+
+        assert!(error(parse_quote!(struct S { #[sii(resolve, default)] a: Vec<A> }))
+            .contains("cannot be optional"));
+    }
+
+    #[test]
+    fn flatten_passes_on_the_need_for_a_save() {
+        let out = expanded(parse_quote! {
+            struct Driver {
+                #[sii(flatten)]
+                skills: Skills,
+                #[sii(flatten)]
+                wear: Wear,
+            }
+        });
+        assert!(out.contains("skills:<Skillsas::siirs::sii::game::__derive::FromStruct>::from_struct(value,save)?"));
+        let bounds = "wherefor<'x>Skills:::siirs::sii::game::__derive::WithoutSave,\
+                      for<'x>Wear:::siirs::sii::game::__derive::WithoutSave,";
+        assert!(out.contains(&format!("WithoutSaveforDriver{bounds}{{}}")));
+        assert!(out.contains(&format!("TryFrom<::siirs::sii::game::__derive::Struct>forDriver{bounds}{{")));
+
+        let out = expanded(parse_quote!(struct Plain { game_time: u32 }));
+        assert!(out.contains("WithoutSaveforPlain{}"));
+    }
+
+    #[test]
+    fn rejects_unknown_attributes_and_shapes() {
+        assert!(error(parse_quote!(#[sii(singular)] struct S { a: u32 }))
+            .contains("unknown sii container attribute"));
+        assert!(error(parse_quote!(struct S { #[sii(renamed = "b")] a: u32 }))
+            .contains("unknown sii field attribute"));
+        assert!(error(parse_quote!(struct S<T> { a: T })).contains("generic"));
+        assert!(error(parse_quote!(struct S(u32);)).contains("named fields"));
+        assert!(error(parse_quote!(enum S { A })).contains("only be derived for structs"));
+    }
+}
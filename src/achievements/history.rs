@@ -0,0 +1,220 @@
+//! Achievement progress over a series of saves, kept in a SQLite database so
+//! it can be charted and extended as new saves come in.
+//!
+//! Every evaluated save stores a snapshot of each requirement; transitions
+//! between statuses are derived from consecutive snapshots, so saves may be
+//! recorded in any order.  Requirements are keyed by their display name, so
+//! keep to one language per store.
+
+use std::path::Path;
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::{AchievementEvaluator, AchievementReport, RequirementStatus};
+
+pub struct ProgressStore {
+    conn: Connection,
+}
+
+/// A requirement's status changing between two saves.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Transition {
+    pub achievement: String,
+    pub requirement: String,
+    /// `None` if this is the first save the requirement was seen in.
+    pub from: Option<RequirementStatus>,
+    pub to: RequirementStatus,
+    /// The save that made the change.
+    pub save: String,
+    pub save_time: u64,
+}
+
+impl ProgressStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn new(conn: Connection) -> Result<Self> {
+        conn.execute_batch(
+            "
+            CREATE TABLE IF NOT EXISTS saves (
+                save TEXT NOT NULL,
+                save_time INTEGER NOT NULL,
+                PRIMARY KEY (save, save_time)
+            );
+            CREATE TABLE IF NOT EXISTS progress (
+                save TEXT NOT NULL,
+                save_time INTEGER NOT NULL,
+                achievement TEXT NOT NULL,
+                requirement TEXT NOT NULL,
+                status TEXT NOT NULL,
+                done INTEGER NOT NULL,
+                required INTEGER NOT NULL,
+                PRIMARY KEY (save, save_time, achievement, requirement)
+            );
+        ",
+        )?;
+        Ok(Self { conn })
+    }
+
+    pub fn has_save(&self, save: &str, save_time: u64) -> Result<bool> {
+        let count: u32 = self.conn.query_row(
+            "SELECT COUNT(1) FROM saves WHERE save = ? AND save_time = ?",
+            params![save, save_time as i64],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Stores the snapshot of every requirement in `report`.
+    pub fn record(&mut self, save: &str, save_time: u64, report: &AchievementReport) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO saves VALUES (?, ?)",
+            params![save, save_time as i64],
+        )?;
+        for a in &report.achievements {
+            for r in &a.requirements {
+                tx.execute(
+                    "INSERT OR REPLACE INTO progress VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    params![
+                        save,
+                        save_time as i64,
+                        a.id,
+                        r.name,
+                        r.status.as_str(),
+                        r.done as i64,
+                        r.required as i64
+                    ],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Every status change, oldest first.  A requirement that is already
+    /// started or completed the first time it is seen counts as a change.
+    pub fn transitions(&self) -> Result<Vec<Transition>> {
+        let mut stmt = self.conn.prepare(
+            "
+            SELECT achievement, requirement, prev, status, save, save_time
+              FROM (SELECT *,
+                           LAG(status) OVER (PARTITION BY achievement, requirement
+                                             ORDER BY save_time, save) AS prev
+                      FROM progress)
+             WHERE (prev IS NULL AND status != 'not_started') OR prev != status
+             ORDER BY save_time, save, achievement, requirement",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (achievement, requirement, from, to, save, save_time) = row?;
+            Ok(Transition {
+                achievement,
+                requirement,
+                from: from.map(|f| f.parse()).transpose()?,
+                to: to.parse()?,
+                save,
+                save_time: save_time as u64,
+            })
+        })
+        .collect()
+    }
+}
+
+/// Evaluates and records each `(path, unix time)` save not already in
+/// `store`.  Returns how many were evaluated.
+pub fn record_history(
+    store: &mut ProgressStore,
+    evaluator: &AchievementEvaluator,
+    saves: &[(String, u64)],
+) -> Result<usize> {
+    let mut evaluated = 0;
+    for (save, save_time) in saves {
+        if store.has_save(save, *save_time)? {
+            continue;
+        }
+
+        let report = evaluator.evaluate(save)?;
+        store.record(save, *save_time, &report)?;
+        evaluated += 1;
+    }
+
+    Ok(evaluated)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use rusqlite::Connection;
+
+    use crate::achievements::{
+        AchievementReport, AchievementStatus, Requirement, RequirementStatus,
+    };
+
+    use super::{ProgressStore, Transition};
+
+    fn report(statuses: &[(&str, RequirementStatus, usize)]) -> AchievementReport {
+        AchievementReport {
+            achievements: vec![AchievementStatus {
+                id: "test".to_owned(),
+                title: "Test".to_owned(),
+                description: None,
+                requirements: statuses
+                    .iter()
+                    .map(|(name, status, done)| Requirement {
+                        name: name.to_string(),
+                        status: *status,
+                        progress_description: String::new(),
+                        done: *done,
+                        required: 2,
+                        country: None,
+                    })
+                    .collect(),
+            }],
+            skipped: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn derives_transitions_from_snapshots() -> Result<()> {
+        use RequirementStatus::*;
+
+        let mut store = ProgressStore::new(Connection::open(":memory:")?)?;
+        // Recorded out of order on purpose.
+        store.record("c", 300, &report(&[("berlin", Completed, 2), ("dresden", Started, 1)]))?;
+        store.record("a", 100, &report(&[("berlin", NotStarted, 0), ("dresden", Started, 1)]))?;
+        store.record("b", 200, &report(&[("berlin", Started, 1), ("dresden", Started, 1)]))?;
+        assert!(store.has_save("b", 200)?);
+        assert!(!store.has_save("b", 201)?);
+
+        let transition = |requirement: &str, from, to, save: &str, save_time| Transition {
+            achievement: "test".to_owned(),
+            requirement: requirement.to_owned(),
+            from,
+            to,
+            save: save.to_owned(),
+            save_time,
+        };
+        assert_eq!(
+            store.transitions()?,
+            [
+                transition("dresden", None, Started, "a", 100),
+                transition("berlin", Some(NotStarted), Started, "b", 200),
+                transition("berlin", Some(Started), Completed, "c", 300),
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! Achievement data read straight from a [`GameSave`], without copying the
+//! save into SQLite first.
+//!
+//! Deliveries are decoded as [`DeliveryLogEntry`]s and indexed by source,
+//! target and cargo, so that a filter on any of them only looks at the
+//! entries it can match.  Values are compared the way the SQLite path's
+//! `v_deliveries` view compares them, so both give the same counts.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use crate::sii::game::{DeliveryLogEntry, GameSave};
+
+/// Deliveries to count.  Every condition that is set must match; a list
+/// matches if any of its values does.
+#[derive(Debug, Default, Clone)]
+pub struct DeliveryFilter {
+    /// Cargo IDs, e.g. `cargo.apples`.
+    pub cargos: Option<Vec<String>>,
+    /// Company IDs, e.g. `company.volatile.posped.berlin`.
+    pub sources: Option<Vec<String>>,
+    pub targets: Option<Vec<String>>,
+    /// City tokens, matched against the end of the company ID.
+    pub source_cities: Option<Vec<String>>,
+    pub target_cities: Option<Vec<String>>,
+    /// In km.
+    pub min_distance: Option<u32>,
+    /// Only deliveries parked without auto park.
+    pub manual_parking: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct DeliveryTotals {
+    pub count: usize,
+    /// Sum of the planned distances, each truncated to whole km.
+    pub distance: i64,
+}
+
+/// A decoded delivery log entry, with the IDs filters compare as strings.
+#[derive(Debug)]
+struct Delivery {
+    entry: DeliveryLogEntry,
+    source: String,
+    target: String,
+    cargo: String,
+}
+
+impl Delivery {
+    fn new(entry: DeliveryLogEntry) -> Self {
+        Self {
+            source: entry.source.to_string(),
+            target: entry.target.to_string(),
+            cargo: entry.cargo.to_string(),
+            entry,
+        }
+    }
+
+    /// The distance in whole km, as the SQLite backend's `CAST` gives it.
+    fn distance(&self) -> Option<i64> {
+        self.entry.distance_km.map(|d| d as i64)
+    }
+
+    fn matches(&self, filter: &DeliveryFilter) -> bool {
+        let any_of = |field: &String, values: &Option<Vec<String>>| {
+            values.as_ref().is_none_or(|values| values.contains(field))
+        };
+        let in_city = |city: Option<String>, cities: &Option<Vec<String>>| {
+            cities
+                .as_ref()
+                .is_none_or(|cities| city.is_some_and(|c| cities.contains(&c)))
+        };
+
+        any_of(&self.cargo, &filter.cargos)
+            && any_of(&self.source, &filter.sources)
+            && any_of(&self.target, &filter.targets)
+            && in_city(self.entry.source_city(), &filter.source_cities)
+            && in_city(self.entry.target_city(), &filter.target_cities)
+            && filter
+                .min_distance
+                .is_none_or(|min| self.distance().is_some_and(|d| d >= min as i64))
+            && (!filter.manual_parking || self.entry.auto_park == Some(false))
+    }
+}
+
+pub struct SaveIndex {
+    deliveries: Vec<Delivery>,
+    by_source: HashMap<String, Vec<usize>>,
+    by_target: HashMap<String, Vec<usize>>,
+    by_cargo: HashMap<String, Vec<usize>>,
+    visited_cities: Option<HashSet<String>>,
+}
+
+impl SaveIndex {
+    /// Fails if a delivery log entry does not decode as a `DeliveryLogEntry`.
+    pub fn new(save: &GameSave) -> Result<Self> {
+        let mut index = Self {
+            deliveries: Vec::new(),
+            by_source: HashMap::new(),
+            by_target: HashMap::new(),
+            by_cargo: HashMap::new(),
+            visited_cities: None,
+        };
+
+        for (_, entry) in save.iter_blocks_named("delivery_log_entry") {
+            let delivery = Delivery::new(DeliveryLogEntry::try_from(entry)?);
+            let i = index.deliveries.len();
+            for (map, key) in [
+                (&mut index.by_source, &delivery.source),
+                (&mut index.by_target, &delivery.target),
+                (&mut index.by_cargo, &delivery.cargo),
+            ] {
+                map.entry(key.clone()).or_default().push(i);
+            }
+            index.deliveries.push(delivery);
+        }
+
+        if let Some(economy) = save.single_block_named("economy") {
+            let cities: Vec<String> = economy.get_opt_as("visited_cities")?.unwrap_or_default();
+            index.visited_cities = Some(cities.into_iter().collect());
+        }
+
+        Ok(index)
+    }
+
+    pub fn deliveries(&self, filter: &DeliveryFilter) -> DeliveryTotals {
+        let mut totals = DeliveryTotals::default();
+        let mut add = |d: &Delivery| {
+            if d.matches(filter) {
+                totals.count += 1;
+                totals.distance += d.distance().unwrap_or(0);
+            }
+        };
+
+        match self.candidates(filter) {
+            Some(candidates) => candidates.into_iter().for_each(|i| add(&self.deliveries[i])),
+            None => self.deliveries.iter().for_each(add),
+        }
+
+        totals
+    }
+
+    /// The deliveries an indexed condition of `filter` allows, from the
+    /// narrowest index; `None` if no indexed condition is set.
+    fn candidates(&self, filter: &DeliveryFilter) -> Option<Vec<usize>> {
+        let lookup = |map: &HashMap<String, Vec<usize>>, values: &Vec<String>| {
+            let mut found = values
+                .iter()
+                .filter_map(|v| map.get(v))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            // A value may be listed twice, but a delivery only counts once.
+            found.sort_unstable();
+            found.dedup();
+            found
+        };
+
+        [
+            (&self.by_source, &filter.sources),
+            (&self.by_target, &filter.targets),
+            (&self.by_cargo, &filter.cargos),
+        ]
+        .into_iter()
+        .filter_map(|(map, values)| values.as_ref().map(|v| lookup(map, v)))
+        .min_by_key(Vec::len)
+    }
+
+    pub fn visited_city(&self, city: &str) -> Result<bool> {
+        let cities = self
+            .visited_cities
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing economy data"))?;
+        Ok(cities.contains(city))
+    }
+}
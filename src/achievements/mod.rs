@@ -1,66 +1,356 @@
 use anyhow::{bail, Result};
 use rusqlite::Connection;
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{collections::{BTreeMap, HashMap}, path::{Path, PathBuf}};
 
 use crate::{
+    game::Game,
+    locale::LocaleDB,
     sii::{self},
-    get_value_as,
+    sii::game::{
+        Company, DeliveryLogEntry, FromGameSave, FromStruct, GameSave, JobBoard, JobOffer,
+        COMPANY_PREFIX,
+    },
     sii::value::{ID, Struct}, scs::Archive, sqlite,
 };
 
-use self::locale::LocaleDB;
-
-mod locale;
+use self::{
+    index::{DeliveryFilter, DeliveryTotals, SaveIndex},
+    names::Names,
+};
+pub mod history;
+pub mod index;
+pub mod names;
+pub mod report;
 
 const ACHIEVEMENTS_SII_HASH: u64 = 0x5C075DC23D8D177;
 
 pub struct AchievementStatus {
-    pub name: String,
+    /// The internal `achievement_name`, e.g. `it_shipyards`.
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
     pub requirements: Vec<Requirement>
 }
 
+impl AchievementStatus {
+    /// The achievement is completed once every requirement is.
+    pub fn status(&self) -> RequirementStatus {
+        let completed = self.completed_count();
+        if completed == self.requirements.len() {
+            RequirementStatus::Completed
+        } else if completed > 0
+            || self.requirements.iter().any(|r| r.status != RequirementStatus::NotStarted)
+        {
+            RequirementStatus::Started
+        } else {
+            RequirementStatus::NotStarted
+        }
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.requirements
+            .iter()
+            .filter(|r| r.status == RequirementStatus::Completed)
+            .count()
+    }
+
+    /// Aggregate progress over all requirements, counting partial progress
+    /// on each, from 0 to 100.
+    pub fn percent_complete(&self) -> f64 {
+        if self.requirements.is_empty() {
+            return 100.0;
+        }
+
+        let total: f64 = self
+            .requirements
+            .iter()
+            .map(|r| match r.status {
+                RequirementStatus::Completed => 1.0,
+                _ if r.required == 0 => 0.0,
+                _ => (r.done as f64 / r.required as f64).min(1.0),
+            })
+            .sum();
+        100.0 * total / self.requirements.len() as f64
+    }
+}
+
+/// An achievement from `achievements.sii` that could not be evaluated.
+#[derive(Clone)]
+pub struct SkippedAchievement {
+    pub id: ID,
+    pub struct_name: String,
+    pub reason: String,
+}
+
+pub struct AchievementReport {
+    pub achievements: Vec<AchievementStatus>,
+    pub skipped: Vec<SkippedAchievement>,
+}
+
+/// An open job that would make progress on an incomplete achievement
+/// requirement.
+pub struct JobSuggestion {
+    pub achievement: String,
+    pub requirement: String,
+    /// The company offering the job.
+    pub source: ID,
+    pub offer: JobOffer,
+}
+
+/// Evaluates every achievement of `game` against the save, with names in
+/// `lang` (e.g. `de_de`).  `mods` are archives whose locale files override the
+/// game's, in increasing order of precedence.
 pub fn get_achievement_status(
     save_path: &str,
-    game_path: &str
-) -> Result<Vec<AchievementStatus>> {
-    let save_parser = sii::binary::Parser::new_from_save(save_path)?;   
-    let mut conn = Connection::open(":memory:")?;
-    sqlite::copy_to_sqlite(save_parser, &mut conn)?;
-    let save_data = AchievementSaveData::new(conn)?;
-
-    let locale_scs_path = PathBuf::from(game_path).join("locale.scs");
-    let locale_db = LocaleDB::new_from_locale_scs(locale_scs_path.to_str().expect("illegal filename"))?;
-    let core_scs_path = PathBuf::from(game_path).join("core.scs");
+    game_path: &str,
+    game: Game,
+    lang: &str,
+    mods: &[PathBuf],
+) -> Result<AchievementReport> {
+    AchievementEvaluator::load(game_path, game, lang, mods)?.evaluate(save_path)
+}
 
-    let mut core = Archive::load_from_path(core_scs_path.to_str().expect("illegal filename"))?;
-    let reader = core.open_entry(ACHIEVEMENTS_SII_HASH)?;
-    let mut parser = sii::text::Parser::new_from_reader(reader)?;
-    let mut results = Vec::new();
-
-    loop {
-        match parser.next() {
-            Some(Ok(t)) => {
-                let achievement: Box<dyn Achievement> = match t.struct_name.as_str() {
-                    "achievement_each_company_data" => Box::from(AchievementEachCompany::try_from(t)?),
-                    "achievement_visit_city_data" => Box::from(AchievementVisitCity::try_from(t)?),
-                    "achievement_each_cargo_data" => Box::from(AchievementEachCargo::try_from(t)?),
-                    _ => { continue; }
-                };
+/// The achievement definitions and names from a game folder, loaded once to
+/// evaluate any number of saves.
+pub struct AchievementEvaluator {
+    names: Names,
+    achievements: Vec<Box<dyn Achievement>>,
+    skipped: Vec<SkippedAchievement>,
+}
 
-                let (name, requirements) = achievement.eval(&save_data, &locale_db)?;
-                results.push(AchievementStatus { name, requirements })
+impl AchievementEvaluator {
+    /// See `get_achievement_status` for `game`, `lang` and `mods`;
+    /// `Game::detect` finds the game of an install and save.
+    pub fn load(game_path: &str, game: Game, lang: &str, mods: &[PathBuf]) -> Result<Self> {
+        Self::load_with(game_path, lang, mods, &AchievementRegistry::for_game(game))
+    }
+
+    /// Like `load`, parsing the game's achievements with `registry`, for the
+    /// registry's game.
+    pub fn load_with(
+        game_path: &str,
+        lang: &str,
+        mods: &[PathBuf],
+        registry: &AchievementRegistry,
+    ) -> Result<Self> {
+        let ldb = LocaleDB::load(Path::new(game_path), registry.game(), lang, mods)?;
+        let names = Names::load(game_path, ldb, registry.game())?;
+        let (achievements, skipped) = load_achievements(game_path, registry)?;
+        Ok(Self { names, achievements, skipped })
+    }
+
+    pub fn add(&mut self, achievement: Box<dyn Achievement>) {
+        self.achievements.push(achievement);
+    }
+
+    /// Adds the goals declared in a text sii file, in the same format as
+    /// `achievements.sii`:
+    ///
+    /// ```text
+    /// SiiNunit
+    /// {
+    /// achievement_visit_city_data : .goal.scandinavia
+    /// {
+    ///  achievement_name: scandinavia
+    ///  event_name: city_visited
+    ///  cities[]: oslo
+    ///  cities[]: stockholm
+    /// }
+    /// }
+    /// ```
+    pub fn add_goals(&mut self, path: &Path, registry: &AchievementRegistry) -> Result<()> {
+        let structs = sii::format::read_structs_from_path(path)?;
+        let (achievements, skipped) = registry.parse_all(structs.into_iter().map(Ok))?;
+        self.achievements.extend(achievements);
+        self.skipped.extend(skipped);
+        Ok(())
+    }
+
+    /// Evaluates the save with the index backend.
+    pub fn evaluate(&self, save_path: &str) -> Result<AchievementReport> {
+        self.evaluate_with(save_path, Backend::Index)
+    }
+
+    pub fn evaluate_with(&self, save_path: &str, backend: Backend) -> Result<AchievementReport> {
+        self.evaluate_data(&load_save_data(save_path, backend)?)
+    }
+
+    pub fn evaluate_data(&self, save_data: &AchievementSaveData) -> Result<AchievementReport> {
+        let mut results = Vec::new();
+
+        for achievement in &self.achievements {
+            let (id, requirements) = achievement.eval(save_data, &self.names)?;
+            let goal = match requirements.as_slice() {
+                [only] => only.required,
+                all => all.len(),
+            };
+            let (title, description) = self.names.achievement(&id, goal);
+            results.push(AchievementStatus { id, title, description, requirements })
+        }
+
+        Ok(AchievementReport { achievements: results, skipped: self.skipped.clone() })
+    }
+
+    /// See `find_achievement_jobs`.
+    pub fn find_jobs(&self, save_path: &str) -> Result<Vec<JobSuggestion>> {
+        let save = GameSave::new_from_save(save_path)?;
+        let save_data = AchievementSaveData::from_game_save(&save)?;
+        let board = JobBoard::from_game_save(&save)?;
+        let mut results = Vec::new();
+
+        for achievement in &self.achievements {
+            let (name, requirements) = achievement.eval(&save_data, &self.names)?;
+            for (i, company, offer) in achievement.matching_jobs(&requirements, &board) {
+                results.push(JobSuggestion {
+                    achievement: name.clone(),
+                    requirement: requirements[i].name.clone(),
+                    source: company.id.clone(),
+                    offer: offer.clone(),
+                });
             }
-            Some(Err(e)) => {
-                bail!(e)
+        }
+
+        Ok(results)
+    }
+
+    /// The locale and def archives that were left out while loading, with
+    /// the reason for each.
+    pub fn skipped_files(&self) -> impl Iterator<Item = &(PathBuf, anyhow::Error)> {
+        self.names.skipped_files()
+    }
+}
+
+/// Finds jobs on the save's job board that would complete a missing
+/// requirement of a company or cargo achievement of `game`.
+pub fn find_achievement_jobs(
+    save_path: &str,
+    game_path: &str,
+    game: Game,
+    lang: &str,
+) -> Result<Vec<JobSuggestion>> {
+    AchievementEvaluator::load(game_path, game, lang, &[])?.find_jobs(save_path)
+}
+
+/// How a save is read for evaluation.  Both give the same results; the
+/// SQLite backend is slower to load but lets custom goals run their own
+/// queries through `AchievementSaveData::conn`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Backend {
+    Sqlite,
+    Index,
+}
+
+pub fn load_save_data(save_path: &str, backend: Backend) -> Result<AchievementSaveData> {
+    match backend {
+        Backend::Sqlite => {
+            let save_parser = sii::binary::Parser::new_from_save(save_path)?;
+            let mut conn = Connection::open(":memory:")?;
+            sqlite::copy_to_sqlite(save_parser, &mut conn)?;
+            AchievementSaveData::new(conn)
+        }
+        Backend::Index => AchievementSaveData::from_game_save(&GameSave::new_from_save(save_path)?),
+    }
+}
+
+pub type LoadedAchievements = (Vec<Box<dyn Achievement>>, Vec<SkippedAchievement>);
+
+fn load_achievements(game_path: &str, registry: &AchievementRegistry) -> Result<LoadedAchievements> {
+    let core_scs_path = PathBuf::from(game_path).join("core.scs");
+    let core = Archive::load_from_path(core_scs_path.to_str().expect("illegal filename"))?;
+    let reader = core.open_entry(ACHIEVEMENTS_SII_HASH)?;
+    registry.parse_all(sii::text::Parser::new_from_reader(reader)?)
+}
+
+/// Builds an achievement from its definition block.
+pub type AchievementParser = Box<dyn Fn(Struct) -> Result<Box<dyn Achievement>>>;
+
+/// Maps achievement data struct names to parsers.  `for_game` knows the
+/// company, city and cargo types of the game's `achievements.sii`; the rest
+/// (ferries, trains, prefabs, delivery counts, ...) are listed as skipped.
+/// Register more to evaluate custom goals.
+pub struct AchievementRegistry {
+    parsers: HashMap<String, AchievementParser>,
+    game: Game,
+}
+
+impl AchievementRegistry {
+    /// An empty registry for ETS2.
+    pub fn new() -> Self {
+        Self { parsers: HashMap::new(), game: Game::default() }
+    }
+
+    /// The built-in achievement types, parsed for `game`.
+    pub fn for_game(game: Game) -> Self {
+        let mut registry = Self { parsers: HashMap::new(), game };
+        registry.register_type::<AchievementEachCompany>("achievement_each_company_data");
+        registry.register_type::<AchievementVisitCity>("achievement_visit_city_data");
+        registry.register_type::<AchievementEachCargo>("achievement_each_cargo_data");
+        registry
+    }
+
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    /// Registers `parser` for `struct_name`, replacing any existing one.
+    pub fn register<F>(&mut self, struct_name: &str, parser: F)
+    where
+        F: Fn(Struct) -> Result<Box<dyn Achievement>> + 'static,
+    {
+        self.parsers.insert(struct_name.to_owned(), Box::new(parser));
+    }
+
+    /// Registers a `TryFrom<Struct>` achievement type for `struct_name`.
+    pub fn register_type<T>(&mut self, struct_name: &str)
+    where
+        T: Achievement + TryFrom<Struct, Error = anyhow::Error> + 'static,
+    {
+        self.register(struct_name, |s| Ok(Box::new(T::try_from(s)?)));
+    }
+
+    /// The struct names there is a parser for.
+    pub fn struct_names(&self) -> impl Iterator<Item = &str> {
+        self.parsers.keys().map(String::as_str)
+    }
+
+    pub fn parse(&self, s: Struct) -> Result<Box<dyn Achievement>> {
+        match self.parsers.get(&s.struct_name) {
+            Some(parser) => parser(s),
+            None => bail!("unsupported achievement type {}", s.struct_name),
+        }
+    }
+
+    /// Parses every block from `structs`, listing the ones that cannot be
+    /// evaluated as skipped.
+    pub fn parse_all<I: Iterator<Item = Result<Struct>>>(&self, structs: I) -> Result<LoadedAchievements> {
+        let mut results = Vec::new();
+        let mut skipped = Vec::new();
+
+        for s in structs {
+            let s = s?;
+            let (id, struct_name) = (s.id.clone(), s.struct_name.clone());
+            match self.parse(s) {
+                Ok(achievement) => results.push(achievement),
+                Err(e) => skipped.push(SkippedAchievement {
+                    id,
+                    struct_name,
+                    reason: e.to_string(),
+                }),
             }
-            None => break,
         }
+
+        Ok((results, skipped))
     }
+}
 
-    Ok(results)
+impl Default for AchievementRegistry {
+    /// The built-in achievement types for ETS2.
+    fn default() -> Self {
+        Self::for_game(Game::default())
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum RequirementStatus {
     NotStarted,
     Started,
@@ -71,32 +361,143 @@ pub struct Requirement {
     pub name: String,
     pub status: RequirementStatus,
     pub progress_description: String,
+    /// Progress made so far, in the same unit as `required`.
+    pub done: usize,
+    pub required: usize,
+    /// Display name of the country the requirement is in, if it is in one.
+    pub country: Option<String>,
 }
 
-trait Achievement {
+/// Something to make progress on.  Implement this for custom goals and add
+/// them with `AchievementEvaluator::add` or an `AchievementRegistry`.
+pub trait Achievement {
+    /// Returns the achievement's id and the status of each requirement.
     fn eval(
         &self,
         save: &AchievementSaveData,
-        ldb: &LocaleDB,
+        names: &Names,
     ) -> Result<(String, Vec<Requirement>)>;
+
+    /// Open jobs on `board` that would make progress on one of the incomplete
+    /// `requirements` returned by `eval`, as (requirement index, company,
+    /// offer).
+    fn matching_jobs<'a>(
+        &self,
+        _requirements: &[Requirement],
+        _board: &'a JobBoard,
+    ) -> Vec<(usize, &'a Company, &'a JobOffer)> {
+        Vec::new()
+    }
+}
+
+/// A save for achievements to query, either loaded into SQLite or indexed
+/// in memory.  Use `deliveries` and `visited_city` to work with both.
+pub struct AchievementSaveData {
+    data: SaveData,
 }
 
-struct AchievementSaveData {
-    conn: Connection,
+enum SaveData {
+    Sqlite(Connection),
+    Index(SaveIndex),
 }
 
 impl AchievementSaveData {
-    fn new(conn: Connection) -> Result<Self> {
-        conn.execute_batch(
+    /// Wraps a connection holding a save copied with
+    /// `sqlite::copy_to_sqlite`.  Besides the save's own tables,
+    /// `temp.v_deliveries` lists the delivery log's job type, source and
+    /// target company, cargo, distance and auto park flag.
+    pub fn new(conn: Connection) -> Result<Self> {
+        conn.execute_batch(&format!(
             "
             CREATE TEMPORARY VIEW IF NOT EXISTS v_deliveries AS
-            SELECT params->>1 AS source,
-                   params->>2 AS target,
-                   params->>3 AS cargo
+            SELECT params->>{} AS job_type,
+                   params->>{} AS source,
+                   params->>{} AS target,
+                   params->>{} AS cargo,
+                   params->>{} AS distance,
+                   params->>{} AS auto_park
               FROM delivery_log_entry;
         ",
+            DeliveryLogEntry::JOB_TYPE,
+            DeliveryLogEntry::SOURCE,
+            DeliveryLogEntry::TARGET,
+            DeliveryLogEntry::CARGO,
+            DeliveryLogEntry::DISTANCE_KM,
+            DeliveryLogEntry::AUTO_PARK,
+        ))?;
+        Ok(Self { data: SaveData::Sqlite(conn) })
+    }
+
+    pub fn from_game_save(save: &GameSave) -> Result<Self> {
+        Ok(Self { data: SaveData::Index(SaveIndex::new(save)?) })
+    }
+
+    /// The SQLite connection, if the save was loaded into one.
+    pub fn conn(&self) -> Option<&Connection> {
+        match &self.data {
+            SaveData::Sqlite(conn) => Some(conn),
+            SaveData::Index(_) => None,
+        }
+    }
+
+    /// Counts the deliveries matching `filter` and adds up their distance.
+    pub fn deliveries(&self, filter: &DeliveryFilter) -> Result<DeliveryTotals> {
+        let conn = match &self.data {
+            SaveData::Sqlite(conn) => conn,
+            SaveData::Index(index) => return Ok(index.deliveries(filter)),
+        };
+
+        let mut query = "
+            SELECT COUNT(1), COALESCE(SUM(CAST(distance AS INTEGER)), 0)
+              FROM temp.v_deliveries
+             WHERE 1"
+            .to_owned();
+        let mut params = Vec::new();
+        for (clause, values) in [
+            ("cargo = ?", &filter.cargos),
+            ("source = ?", &filter.sources),
+            ("target = ?", &filter.targets),
+            // Companies are COMPANY_PREFIX, then <company>.<city>.
+            ("source GLOB '*.' || ?", &filter.source_cities),
+            ("target GLOB '*.' || ?", &filter.target_cities),
+        ] {
+            let Some(values) = values else {
+                continue;
+            };
+            if values.is_empty() {
+                query += " AND 0";
+                continue;
+            }
+            query += &format!(" AND ({})", vec![clause; values.len()].join(" OR "));
+            params.extend(values.iter().cloned());
+        }
+        if let Some(min_distance) = filter.min_distance {
+            query += " AND CAST(distance AS INTEGER) >= ?";
+            params.push(min_distance.to_string());
+        }
+        if filter.manual_parking {
+            query += " AND auto_park IN ('0', 'false')";
+        }
+
+        let (count, distance) = conn.query_row(
+            &query,
+            rusqlite::params_from_iter(params),
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
-        Ok(Self { conn })
+        Ok(DeliveryTotals { count, distance })
+    }
+
+    pub fn visited_city(&self, city: &str) -> Result<bool> {
+        let conn = match &self.data {
+            SaveData::Sqlite(conn) => conn,
+            SaveData::Index(index) => return index.visited_city(city),
+        };
+
+        Ok(conn.query_row(
+            "SELECT ? IN (SELECT value FROM json_each(visited_cities)) FROM ECONOMY",
+            [city],
+            |row| row.get(0),
+        )?)
     }
 }
 
@@ -108,48 +509,40 @@ struct AchievementEachCompany {
     required_cargo: Option<Vec<String>>,
 }
 
+#[derive(FromStruct)]
+#[sii(struct_name = "achievement_each_company_data")]
+struct EachCompanyData {
+    achievement_name: String,
+    #[sii(optional)]
+    sources: Option<Vec<String>>,
+    #[sii(optional)]
+    targets: Option<Vec<String>>,
+    #[sii(optional)]
+    cargos: Option<Vec<String>>,
+}
+
 impl TryFrom<Struct> for AchievementEachCompany {
     type Error = anyhow::Error;
 
     fn try_from(value: Struct) -> Result<Self> {
-        if value.struct_name != "achievement_each_company_data" {
-            bail!(
-                "cannot decode AchievementEachCompany from {}",
-                value.struct_name
-            );
-        }
-
-        let match_field = if value.fields.contains_key("sources") {
-            "sources"
-        } else if value.fields.contains_key("targets") {
-            "targets"
-        } else {
-            bail!("achievement {:?} lacks sources or targets", value.id)
-        };
-
-        let required_cargo = if value.fields.contains_key("cargos") {
-            Some(get_value_as!(value, "cargos", StringArray)?.clone())
-        } else {
-            None
+        let data = EachCompanyData::try_from(&value)?;
+        let (match_field, target_arr) = match (data.sources, data.targets) {
+            (Some(sources), _) => ("sources", sources),
+            (None, Some(targets)) => ("targets", targets),
+            (None, None) => bail!("achievement {:?} lacks sources or targets", value.id),
         };
 
-        let achievement_name = get_value_as!(value, "achievement_name", String)?.to_owned();
         let mut companies = BTreeMap::new();
-        let target_arr = get_value_as!(value, match_field, StringArray)?;
         for t in target_arr {
             let target_id = ID::try_from(t.as_str())?;
-            if !companies.contains_key(&target_id) {
-                companies.insert(target_id.clone(), 0);
-            }
-
-            *(companies.get_mut(&target_id).unwrap()) += 1;
+            *companies.entry(target_id).or_insert(0) += 1;
         }
 
         Ok(Self {
-            achievement_name,
+            achievement_name: data.achievement_name,
             match_field,
             companies,
-            required_cargo,
+            required_cargo: data.cargos,
         })
     }
 }
@@ -158,27 +551,23 @@ impl Achievement for AchievementEachCompany {
     fn eval(
         &self,
         save: &AchievementSaveData,
-        _ldb: &LocaleDB,
+        names: &Names,
     ) -> Result<(String, Vec<Requirement>)> {
-        let mut query = format!(
-            "SELECT COUNT(1) FROM temp.v_deliveries WHERE {} = ?",
-            &self.match_field[..self.match_field.len() - 1]
-        );
-        let cargo_params = if let Some(ref cargo) = self.required_cargo {
-            query += &format!(" AND cargo IN ({})", sqlite_placeholders(cargo.len()));
-            cargo.iter().map(|c| format!("cargo.{}", c)).collect()
-        } else {
-            vec![]
-        };
+        let cargos = self
+            .required_cargo
+            .as_ref()
+            .map(|cargo| cargo.iter().map(|c| format!("cargo.{}", c)).collect());
         let requirements = self
             .companies
             .iter()
             .map(|(t, c)| {
-                let mut params = vec![format!("company.volatile.{}", t.to_string())];
-                params.append(&mut cargo_params.clone());
-                let completed: usize =
-                    save.conn
-                        .query_row(&query, rusqlite::params_from_iter(params), |row| row.get(0))?;
+                let company = Some(vec![format!("{}{}", COMPANY_PREFIX, t.to_string())]);
+                let filter = if self.match_field == "sources" {
+                    DeliveryFilter { sources: company, cargos: cargos.clone(), ..Default::default() }
+                } else {
+                    DeliveryFilter { targets: company, cargos: cargos.clone(), ..Default::default() }
+                };
+                let completed = save.deliveries(&filter)?.count;
 
                 let status = if completed >= *c {
                     RequirementStatus::Completed
@@ -189,55 +578,85 @@ impl Achievement for AchievementEachCompany {
                 };
 
                 Ok(Requirement {
-                    name: t.to_string(),
+                    name: names.company(&t.to_string()),
                     status,
                     progress_description: format!("{}/{}", completed, c),
+                    done: completed,
+                    required: *c,
+                    country: t
+                        .to_string()
+                        .split_once('.')
+                        .and_then(|(_, city)| names.country(city)),
                 })
             })
             .collect::<Result<Vec<_>>>()?;
 
         Ok((self.achievement_name.to_owned(), requirements))
     }
+
+    fn matching_jobs<'a>(
+        &self,
+        requirements: &[Requirement],
+        board: &'a JobBoard,
+    ) -> Vec<(usize, &'a Company, &'a JobOffer)> {
+        let mut out = Vec::new();
+        for (i, (company, req)) in self.companies.keys().zip(requirements).enumerate() {
+            if req.status == RequirementStatus::Completed {
+                continue;
+            }
+            let wanted = format!("{}{}", COMPANY_PREFIX, company.to_string());
+            let Ok(wanted) = ID::try_from(wanted) else {
+                continue;
+            };
+
+            for (c, offer) in board.open_jobs() {
+                let endpoint = if self.match_field == "sources" {
+                    Some(&c.id)
+                } else {
+                    offer.target.as_ref()
+                };
+                let cargo_ok = match (&self.required_cargo, offer.cargo_name()) {
+                    (None, _) => true,
+                    (Some(cargos), Some(cargo)) => cargos.contains(&cargo),
+                    (Some(_), None) => false,
+                };
+                if endpoint == Some(&wanted) && cargo_ok {
+                    out.push((i, c, offer));
+                }
+            }
+        }
+
+        out
+    }
 }
 
+#[derive(FromStruct)]
+#[sii(struct_name = "achievement_visit_city_data")]
 struct AchievementVisitCity {
     achievement_name: String,
+    #[sii(with = "visited_cities")]
     cities: Vec<String>,
 }
 
-impl TryFrom<Struct> for AchievementVisitCity {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Struct) -> Result<Self> {
-        if get_value_as!(value, "event_name", String)? != "city_visited" {
-            bail!("expected achievement_visit_city_data to have event_name = city_visited");
-        }
-
-        let achievement_name = get_value_as!(value, "achievement_name", String)?.clone();
-        let cities = get_value_as!(value, "cities", StringArray)?.clone();
-        Ok(Self {
-            achievement_name,
-            cities,
-        })
+fn visited_cities(value: &Struct) -> Result<Vec<String>> {
+    if value.get_as::<String>("event_name")? != "city_visited" {
+        bail!("expected achievement_visit_city_data to have event_name = city_visited");
     }
+
+    value.get_as("cities")
 }
 
 impl Achievement for AchievementVisitCity {
     fn eval(
         &self,
         save: &AchievementSaveData,
-        ldb: &LocaleDB,
+        names: &Names,
     ) -> Result<(String, Vec<Requirement>)> {
-        let query = "
-            SELECT (CASE WHEN ? IN (SELECT value FROM json_each(visited_cities))
-                    THEN 1
-                    ELSE 0 END)
-              FROM ECONOMY;";
         let requirements = self
             .cities
             .iter()
             .map(|c| {
-                let completed: usize = save.conn.query_row(&query, [c], |row| row.get(0))?;
+                let completed = usize::from(save.visited_city(c)?);
 
                 let status = if completed > 0 {
                     RequirementStatus::Completed
@@ -246,9 +665,12 @@ impl Achievement for AchievementVisitCity {
                 };
 
                 Ok(Requirement {
-                    name: ldb.try_localize(c).unwrap_or(c).to_string(),
+                    name: names.city(c),
                     status,
                     progress_description: "visit".to_owned(),
+                    done: completed,
+                    required: 1,
+                    country: names.country(c),
                 })
             })
             .collect::<Result<Vec<_>>>()?;
@@ -257,47 +679,28 @@ impl Achievement for AchievementVisitCity {
     }
 }
 
+#[derive(FromStruct)]
+#[sii(struct_name = "achievement_each_cargo_data")]
 struct AchievementEachCargo {
     achievement_name: String,
     cargos: Vec<String>,
 }
 
-impl TryFrom<Struct> for AchievementEachCargo {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Struct) -> Result<Self> {
-        if value.struct_name != "achievement_each_cargo_data" {
-            bail!(
-                "cannot decode AchievementEachCargo from {}",
-                value.struct_name
-            );
-        }
-
-        let cargos = get_value_as!(value, "cargos", StringArray)?.clone();
-        let achievement_name = get_value_as!(value, "achievement_name", String)?.to_owned();
-
-        Ok(Self {
-            achievement_name,
-            cargos,
-        })
-    }
-}
-
 impl Achievement for AchievementEachCargo {
     fn eval(
         &self,
         save: &AchievementSaveData,
-        ldb: &LocaleDB,
+        names: &Names,
     ) -> Result<(String, Vec<Requirement>)> {
         let requirements = self
             .cargos
             .iter()
             .map(|c| {
-                let completed: usize = save.conn.query_row(
-                    "SELECT COUNT(1) FROM temp.v_deliveries WHERE cargo = 'cargo.' || ?",
-                    [c],
-                    |row| row.get(0),
-                )?;
+                let filter = DeliveryFilter {
+                    cargos: Some(vec![format!("cargo.{}", c)]),
+                    ..Default::default()
+                };
+                let completed = save.deliveries(&filter)?.count;
 
                 let status = if completed > 0 {
                     RequirementStatus::Completed
@@ -306,22 +709,340 @@ impl Achievement for AchievementEachCargo {
                 };
 
                 Ok(Requirement {
-                    name: ldb
-                        .try_localize(&format!("cn_{}", c))
-                        .unwrap_or(c)
-                        .to_string(),
+                    name: names.cargo(c),
                     status,
                     progress_description: format!("{}/1", completed),
+                    done: completed,
+                    required: 1,
+                    country: None,
                 })
             })
             .collect::<Result<Vec<_>>>()?;
 
         Ok((self.achievement_name.to_owned(), requirements))
     }
+
+    fn matching_jobs<'a>(
+        &self,
+        requirements: &[Requirement],
+        board: &'a JobBoard,
+    ) -> Vec<(usize, &'a Company, &'a JobOffer)> {
+        let mut out = Vec::new();
+        for (i, (cargo, req)) in self.cargos.iter().zip(requirements).enumerate() {
+            if req.status == RequirementStatus::Completed {
+                continue;
+            }
+
+            for (c, offer) in board.open_jobs() {
+                if offer.cargo_name().as_ref() == Some(cargo) {
+                    out.push((i, c, offer));
+                }
+            }
+        }
+
+        out
+    }
 }
 
-fn sqlite_placeholders(count: usize) -> String {
-    let mut s = "?,".repeat(count);
-    s.pop();
-    s
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Result;
+
+    use crate::{
+        defs::GameDefs,
+        game::Game,
+        locale::LocaleDB,
+        scs::Archive,
+        sii::{
+            game::{FromGameSave, GameSave, JobBoard},
+            value::{EncodedString, Value, ID},
+        },
+        test_util::block,
+    };
+
+    use rusqlite::Connection;
+
+    use super::{
+        ACHIEVEMENTS_SII_HASH,
+        index::{DeliveryFilter, DeliveryTotals},
+        names::Names, Achievement, AchievementRegistry,
+        AchievementEachCompany, AchievementSaveData, Requirement, RequirementStatus,
+    };
+
+    fn requirement(status: RequirementStatus) -> Requirement {
+        Requirement {
+            name: String::new(),
+            status,
+            progress_description: String::new(),
+            done: 0,
+            required: 1,
+            country: None,
+        }
+    }
+
+    #[test]
+    fn matches_jobs_to_incomplete_company_requirements() -> Result<()> {
+        let achievement = AchievementEachCompany::try_from(block(
+            ID::try_from(".achievement.test")?,
+            "achievement_each_company_data",
+            vec![
+                ("achievement_name", Value::String("test".to_owned())),
+                (
+                    "targets",
+                    Value::StringArray(vec!["posped.dresden".to_owned(), "posped.berlin".to_owned()]),
+                ),
+            ],
+        ))?;
+
+        let offer = |id, target: &str| {
+            block(
+                ID::Nameless(id),
+                "job_offer_data",
+                vec![
+                    ("target", Value::String(target.to_owned())),
+                    ("cargo", Value::ID(ID::try_from("cargo.apples").unwrap())),
+                    ("expiration_time", Value::UInt32(100)),
+                ],
+            )
+        };
+        let save: GameSave = [
+            block(ID::Nameless(1), "economy", vec![("game_time", Value::UInt32(0))]),
+            block(
+                ID::try_from("company.volatile.tradeaux.leipzig")?,
+                "company",
+                vec![("job_offer", Value::IDArray(vec![ID::Nameless(2), ID::Nameless(3)]))],
+            ),
+            offer(2, "posped.dresden"),
+            offer(3, "posped.berlin"),
+        ]
+        .into_iter()
+        .collect();
+        let board = JobBoard::from_game_save(&save)?;
+
+        // Requirements are in company order: berlin, then dresden.
+        let requirements = [
+            requirement(RequirementStatus::Completed),
+            requirement(RequirementStatus::Started),
+        ];
+        let jobs = achievement.matching_jobs(&requirements, &board);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].0, 1);
+        assert_eq!(jobs[0].2.target_city().as_deref(), Some("dresden"));
+        Ok(())
+    }
+
+    #[test]
+    fn counts_deliveries_matching_filters() -> Result<()> {
+        let conn = Connection::open(":memory:")?;
+        conn.execute("CREATE TABLE delivery_log_entry (struct_id, params)", ())?;
+        for params in [
+            r#"["1","company.volatile.posped.berlin","company.volatile.tradeaux.dresden","cargo.apples","0","0","190","0"]"#,
+            r#"["1","company.volatile.posped.berlin","company.volatile.tradeaux.calais","cargo.apples","0","0","1020","1"]"#,
+            r#"["1","company.volatile.posped.berlin","company.volatile.gnt.dresden","cargo.milk","0","0","190","0"]"#,
+        ] {
+            conn.execute("INSERT INTO delivery_log_entry VALUES (NULL, ?)", [params])?;
+        }
+        let save = AchievementSaveData::new(conn)?;
+        let strings = |values: &[&str]| Some(values.iter().map(|v| v.to_string()).collect());
+
+        let apples = DeliveryFilter { cargos: strings(&["cargo.apples"]), ..Default::default() };
+        assert_eq!(save.deliveries(&apples)?, DeliveryTotals { count: 2, distance: 1210 });
+
+        let to_dresden = DeliveryFilter { target_cities: strings(&["dresden"]), ..Default::default() };
+        assert_eq!(save.deliveries(&to_dresden)?, DeliveryTotals { count: 2, distance: 380 });
+
+        let parked = DeliveryFilter {
+            min_distance: Some(500),
+            manual_parking: true,
+            ..Default::default()
+        };
+        assert_eq!(save.deliveries(&parked)?.count, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn index_matches_sqlite() -> Result<()> {
+        let deliveries: [&[&str]; 5] = [
+            &["1", "company.volatile.posped.berlin", "company.volatile.tradeaux.dresden", "cargo.apples", "0", "0", "190", "0"],
+            &["1", "company.volatile.posped.berlin", "company.volatile.tradeaux.calais", "cargo.apples", "0", "0", "1020", "1"],
+            &["2", "company.volatile.gnt.dresden", "company.volatile.posped.berlin", "cargo.milk", "0", "0", "190.5", "false"],
+            &["0", "company.volatile.gnt.dresden", "company.volatile.posped.berlin", "cargo.milk", "0", "0", "400"],
+            &["1", "company.volatile.posped.berlin", "company.volatile.gnt.dresden", "cargo.logs", "0", "0"],
+        ];
+        let cities = ["berlin", "dresden"];
+
+        let conn = Connection::open(":memory:")?;
+        conn.execute_batch(
+            "CREATE TABLE delivery_log_entry (struct_id, params);
+             CREATE TABLE economy (struct_id, visited_cities);",
+        )?;
+        let json = |values: &[&str]| {
+            format!("[{}]", values.iter().map(|v| format!("\"{}\"", v)).collect::<Vec<_>>().join(","))
+        };
+        for params in deliveries {
+            conn.execute("INSERT INTO delivery_log_entry VALUES (NULL, ?)", [json(params)])?;
+        }
+        conn.execute("INSERT INTO economy VALUES (NULL, ?)", [json(&cities)])?;
+        let sqlite = AchievementSaveData::new(conn)?;
+
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let mut blocks = deliveries
+            .iter()
+            .enumerate()
+            .map(|(i, params)| {
+                block(
+                    ID::Nameless(i as u64 + 1),
+                    "delivery_log_entry",
+                    vec![("params", Value::StringArray(strings(params)))],
+                )
+            })
+            .collect::<Vec<_>>();
+        blocks.push(block(
+            ID::Nameless(100),
+            "economy",
+            vec![("visited_cities", Value::EncodedStringArray(
+                cities.iter().map(|c| EncodedString::try_from(*c)).collect::<Result<_>>()?,
+            ))],
+        ));
+        let index = AchievementSaveData::from_game_save(&blocks.into_iter().collect())?;
+
+        let names = Names::new(LocaleDB::new_empty(), GameDefs::default(), Game::Ets2);
+        let list = |values: &[&str]| Value::StringArray(strings(values));
+        let name = || ("achievement_name", Value::String("test".to_owned()));
+        for (struct_name, fields) in [
+            ("achievement_each_company_data", vec![name(), ("sources", list(&["posped.berlin", "gnt.dresden"]))]),
+            (
+                "achievement_each_company_data",
+                vec![name(), ("targets", list(&["posped.berlin", "posped.berlin"])), ("cargos", list(&["milk"]))],
+            ),
+            ("achievement_each_cargo_data", vec![name(), ("cargos", list(&["apples", "milk", "logs"]))]),
+            (
+                "achievement_visit_city_data",
+                vec![name(), ("event_name", Value::String("city_visited".to_owned())), ("cities", list(&["berlin", "oslo"]))],
+            ),
+        ] {
+            let achievement = AchievementRegistry::default()
+                .parse(block(ID::try_from(".achievement.test")?, struct_name, fields))?;
+            let summarize = |save| -> Result<Vec<_>> {
+                Ok(achievement
+                    .eval(save, &names)?
+                    .1
+                    .into_iter()
+                    .map(|r| (r.name, r.status, r.done, r.required, r.progress_description))
+                    .collect())
+            };
+            assert_eq!(summarize(&index)?, summarize(&sqlite)?, "{}", struct_name);
+        }
+
+        let ids = |values: &[&str]| Some(strings(values));
+        for filter in [
+            DeliveryFilter { source_cities: ids(&["berlin", "dresden"]), ..Default::default() },
+            DeliveryFilter {
+                cargos: ids(&["cargo.apples", "cargo.apples"]),
+                targets: ids(&["company.volatile.tradeaux.calais"]),
+                ..Default::default()
+            },
+            DeliveryFilter { min_distance: Some(190), manual_parking: true, ..Default::default() },
+            DeliveryFilter { sources: ids(&[]), ..Default::default() },
+        ] {
+            assert_eq!(index.deliveries(&filter)?, sqlite.deliveries(&filter)?, "{:?}", filter);
+        }
+        Ok(())
+    }
+
+    /// Every built-in type must be used by the shipped `achievements.sii`, and
+    /// every achievement of those types must parse.
+    #[test]
+    #[ignore = "needs the game's core.scs in test_symlinks/game_path"]
+    fn evaluates_shipped_achievement_types() -> Result<()> {
+        let core = Archive::load_from_path("test_symlinks/game_path/core.scs")?;
+        let structs = crate::sii::text::Parser::new_from_reader(core.open_entry(ACHIEVEMENTS_SII_HASH)?)?
+            .collect::<Result<Vec<_>>>()?;
+        let shipped = structs.iter().map(|s| s.struct_name.as_str()).collect::<HashSet<_>>();
+
+        let registry = AchievementRegistry::default();
+        let mut unused = registry.struct_names().filter(|n| !shipped.contains(n)).collect::<Vec<_>>();
+        unused.sort();
+        assert!(unused.is_empty(), "not in achievements.sii: {:?}", unused);
+
+        let (_, skipped) = registry.parse_all(structs.iter().cloned().map(Ok))?;
+        let supported = registry.struct_names().collect::<HashSet<_>>();
+        let bad = skipped
+            .iter()
+            .filter(|s| supported.contains(s.struct_name.as_str()))
+            .map(|s| format!("{:?} ({}): {}", s.id, s.struct_name, s.reason))
+            .collect::<Vec<_>>();
+        assert!(bad.is_empty(), "{bad:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn refuses_unknown_achievements() -> Result<()> {
+        let id = ID::try_from(".achievement.test")?;
+        let name = ("achievement_name", Value::String("test".to_owned()));
+        for (struct_name, fields) in [
+            ("achievement_ferry_data", vec![name.clone()]),
+            ("achievement_something_new_data", vec![name.clone()]),
+        ] {
+            let registry = AchievementRegistry::default();
+            assert!(registry.parse(block(id.clone(), struct_name, fields)).is_err());
+        }
+        Ok(())
+    }
+
+    struct TotalDeliveries(u32);
+
+    impl Achievement for TotalDeliveries {
+        fn eval(&self, save: &AchievementSaveData, _names: &Names) -> Result<(String, Vec<Requirement>)> {
+            let done = save.deliveries(&DeliveryFilter::default())?.count;
+            let mut requirement = requirement(RequirementStatus::Started);
+            requirement.done = done;
+            requirement.required = self.0 as usize;
+            Ok(("total".to_owned(), vec![requirement]))
+        }
+    }
+
+    #[test]
+    fn registers_custom_goals() -> Result<()> {
+        let mut registry = AchievementRegistry::default();
+        registry.register("goal_total_deliveries", |s| {
+            Ok(Box::new(TotalDeliveries(s.get_as("target")?)))
+        });
+        let goals = crate::sii::text::Parser::new_from_reader(
+            &b"SiiNunit
+{
+goal_total_deliveries : .goal.total
+{
+ target: 10
+}
+achievement_visit_city_data : .goal.scandinavia
+{
+ achievement_name: scandinavia
+ event_name: city_visited
+ cities[]: oslo
+ cities[]: stockholm
+}
+goal_unknown : .goal.unknown
+{
+}
+}
+"[..],
+        )?;
+        let (achievements, skipped) = registry.parse_all(goals)?;
+        assert_eq!(achievements.len(), 2);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].struct_name, "goal_unknown");
+
+        let conn = Connection::open(":memory:")?;
+        conn.execute("CREATE TABLE delivery_log_entry (struct_id, params)", ())?;
+        conn.execute("INSERT INTO delivery_log_entry VALUES (NULL, '[]')", ())?;
+        let save = AchievementSaveData::new(conn)?;
+        let names = Names::new(LocaleDB::new_empty(), GameDefs::default(), Game::Ets2);
+        let (id, requirements) = achievements[0].eval(&save, &names)?;
+        assert_eq!(id, "total");
+        assert_eq!((requirements[0].done, requirements[0].required), (1, 10));
+        Ok(())
+    }
 }
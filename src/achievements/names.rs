@@ -0,0 +1,143 @@
+//! Display names for the companies, cities and cargo achievements refer to,
+//! read from the def files the way the game's UI shows them.  Anything that
+//! cannot be resolved falls back to its raw ID.
+
+use std::{fmt::Display, path::{Path, PathBuf}};
+
+use anyhow::Result;
+
+use crate::{defs::GameDefs, game::Game, locale::LocaleDB};
+
+pub struct Names {
+    ldb: LocaleDB,
+    defs: GameDefs,
+    game: Game,
+}
+
+impl Names {
+    pub fn new(ldb: LocaleDB, defs: GameDefs, game: Game) -> Self {
+        Self { ldb, defs, game }
+    }
+
+    /// Loads names from `def.scs` and any DLC archives in the game folder.
+    pub fn load(game_path: &str, ldb: LocaleDB, game: Game) -> Result<Self> {
+        let defs = GameDefs::load(Path::new(game_path), &ldb)?;
+        Ok(Self::new(ldb, defs, game))
+    }
+
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    /// The locale and def archives that were left out while loading, with
+    /// the reason for each.
+    pub fn skipped_files(&self) -> impl Iterator<Item = &(PathBuf, anyhow::Error)> {
+        self.ldb.skipped().iter().chain(&self.defs.skipped)
+    }
+
+    /// A distance in km, in the game's unit.
+    pub fn distance(&self, km: f64) -> String {
+        self.game.format_distance(km)
+    }
+
+    fn localize_key(&self, key: &str, args: &[&dyn Display]) -> Option<String> {
+        self.ldb.localize_with(key, args).and_then(Result::ok)
+    }
+
+    pub fn city(&self, city: &str) -> String {
+        self.defs
+            .cities
+            .get(city)
+            .and_then(|c| c.name.clone())
+            .or_else(|| self.localize_key(city, &[]))
+            .unwrap_or(city.to_owned())
+    }
+
+    pub fn country(&self, city: &str) -> Option<String> {
+        self.defs.country_of_city(city).and_then(|c| c.name.clone())
+    }
+
+    pub fn cargo(&self, cargo: &str) -> String {
+        self.defs
+            .cargos
+            .get(cargo)
+            .and_then(|c| c.name.clone())
+            .or_else(|| self.localize_key(&format!("cn_{}", cargo), &[]))
+            .unwrap_or(cargo.to_owned())
+    }
+
+    /// The title and description of an achievement, by `achievement_name`.
+    /// `goal` fills in the description's count placeholder, if it has one.
+    pub fn achievement(&self, id: &str, goal: usize) -> (String, Option<String>) {
+        let title = self
+            .localize_key(&format!("achievement_{}", id), &[])
+            .unwrap_or(id.to_owned());
+        let description = self.localize_key(&format!("achievement_{}_desc", id), &[&goal]);
+        (title, description)
+    }
+
+    fn company_name(&self, company: &str) -> Option<String> {
+        self.defs.companies.get(company).and_then(|c| c.name.clone())
+    }
+
+    /// Names a company depot given as `<company>.<city>`.
+    pub fn company(&self, depot: &str) -> String {
+        match depot.split_once('.') {
+            Some((company, city)) => match self.company_name(company) {
+                Some(name) => format!("{} ({})", name, self.city(city)),
+                None => depot.to_owned(),
+            },
+            None => self.company_name(depot).unwrap_or(depot.to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{defs::GameDefs, game::Game, locale::LocaleDB, sii::format};
+
+    use super::Names;
+
+    #[test]
+    fn resolves_names_through_defs() -> Result<()> {
+        let locale = b"SiiNunit
+{
+localization_db : .localization
+{
+ key[]: \"berlin\"
+ val[]: \"Berlin\"
+ key[]: \"cn_apples\"
+ val[]: \"Apples\"
+}
+}
+";
+        let ldb = LocaleDB::new_from_reader(&mut &locale[..])?;
+        let mut defs = GameDefs::default();
+        defs.add_structs(&format::read_fragment_structs(
+            b"city_data: city.berlin
+{
+ city_name: \"Berlin (unlocalized)\"
+ city_name_localized: \"@@berlin@@\"
+}
+company_permanent: company.permanent.posped
+{
+ name: \"Posped\"
+}
+cargo_data: cargo.apples
+{
+ name: \"@@cn_apples@@\"
+}
+",
+        )?, &ldb);
+        let names = Names::new(ldb, defs, Game::Ats);
+
+        assert_eq!(names.company("posped.berlin"), "Posped (Berlin)");
+        assert_eq!(names.company("tradeaux.berlin"), "tradeaux.berlin");
+        assert_eq!(names.city("dresden"), "dresden");
+        assert_eq!(names.cargo("apples"), "Apples");
+        assert_eq!(names.distance(1000.0), "621 mi");
+        Ok(())
+    }
+}
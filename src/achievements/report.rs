@@ -0,0 +1,311 @@
+//! Renders an [`AchievementReport`] as plain text, JSON, CSV or Markdown.
+
+use std::{io::Write, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use super::{AchievementReport, AchievementStatus, RequirementStatus};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "text" => Self::Text,
+            "json" => Self::Json,
+            "csv" => Self::Csv,
+            "markdown" | "md" => Self::Markdown,
+            _ => bail!("unknown report format {:?} (text, json, csv, markdown)", s),
+        })
+    }
+}
+
+impl RequirementStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequirementStatus::NotStarted => "not_started",
+            RequirementStatus::Started => "started",
+            RequirementStatus::Completed => "completed",
+        }
+    }
+}
+
+impl FromStr for RequirementStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "not_started" => RequirementStatus::NotStarted,
+            "started" => RequirementStatus::Started,
+            "completed" => RequirementStatus::Completed,
+            _ => bail!("unknown requirement status {:?}", s),
+        })
+    }
+}
+
+pub fn write_report<W: Write>(w: &mut W, report: &AchievementReport, format: ReportFormat) -> Result<()> {
+    match format {
+        ReportFormat::Text => write_text(w, report),
+        ReportFormat::Json => write_json(w, report),
+        ReportFormat::Csv => write_csv(w, report),
+        ReportFormat::Markdown => write_markdown(w, report),
+    }
+}
+
+fn write_text<W: Write>(w: &mut W, report: &AchievementReport) -> Result<()> {
+    for a in &report.achievements {
+        let title = format!("{} ({:.0}%)", a.title, a.percent_complete());
+        writeln!(w, "╭─{}─╮", "─".repeat(title.chars().count()))?;
+        writeln!(w, "│ {} │", title)?;
+        writeln!(w, "╰─{}─╯", "─".repeat(title.chars().count()))?;
+        if let Some(description) = &a.description {
+            writeln!(w, "{}", description)?;
+        }
+        for req in &a.requirements {
+            let mark = if req.status == RequirementStatus::Completed { "✓" } else { " " };
+            writeln!(w, "{} {}: {}", mark, req.progress_description, req.name)?;
+        }
+        writeln!(w)?;
+    }
+
+    if !report.skipped.is_empty() {
+        writeln!(w, "Skipped {} achievements:", report.skipped.len())?;
+        for s in &report.skipped {
+            writeln!(w, "  {} ({}): {}", s.id.to_string(), s.struct_name, s.reason)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_json<W: Write>(w: &mut W, report: &AchievementReport) -> Result<()> {
+    let achievements = report
+        .achievements
+        .iter()
+        .map(achievement_json)
+        .collect::<Vec<_>>();
+    let skipped = report
+        .skipped
+        .iter()
+        .map(|s| {
+            format!(
+                "{{\"id\":{},\"struct_name\":{},\"reason\":{}}}",
+                json_string(&s.id.to_string()),
+                json_string(&s.struct_name),
+                json_string(&s.reason)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    writeln!(
+        w,
+        "{{\"achievements\":[{}],\"skipped\":[{}]}}",
+        achievements.join(","),
+        skipped.join(",")
+    )?;
+    Ok(())
+}
+
+fn achievement_json(a: &AchievementStatus) -> String {
+    let requirements = a
+        .requirements
+        .iter()
+        .map(|r| {
+            format!(
+                "{{\"name\":{},\"status\":\"{}\",\"done\":{},\"required\":{},\"progress\":{},\"country\":{}}}",
+                json_string(&r.name),
+                r.status.as_str(),
+                r.done,
+                r.required,
+                json_string(&r.progress_description),
+                r.country.as_deref().map_or("null".to_owned(), json_string)
+            )
+        })
+        .collect::<Vec<_>>();
+    let description = match &a.description {
+        Some(d) => json_string(d),
+        None => "null".to_owned(),
+    };
+
+    format!(
+        "{{\"id\":{},\"title\":{},\"description\":{},\"status\":\"{}\",\
+         \"percent_complete\":{:.1},\"completed\":{},\"total\":{},\"requirements\":[{}]}}",
+        json_string(&a.id),
+        json_string(&a.title),
+        description,
+        a.status().as_str(),
+        a.percent_complete(),
+        a.completed_count(),
+        a.requirements.len(),
+        requirements.join(",")
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// One row per requirement.
+fn write_csv<W: Write>(w: &mut W, report: &AchievementReport) -> Result<()> {
+    writeln!(w, "achievement_id,achievement_title,requirement,status,done,required,progress,country")?;
+    for a in &report.achievements {
+        for r in &a.requirements {
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{},{}",
+                csv_field(&a.id),
+                csv_field(&a.title),
+                csv_field(&r.name),
+                r.status.as_str(),
+                r.done,
+                r.required,
+                csv_field(&r.progress_description),
+                csv_field(r.country.as_deref().unwrap_or_default())
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn write_markdown<W: Write>(w: &mut W, report: &AchievementReport) -> Result<()> {
+    for a in &report.achievements {
+        writeln!(w, "## {}", a.title)?;
+        writeln!(w)?;
+        if let Some(description) = &a.description {
+            writeln!(w, "_{}_", description)?;
+            writeln!(w)?;
+        }
+        writeln!(
+            w,
+            "**{:.0}%** ({}/{} requirements)",
+            a.percent_complete(),
+            a.completed_count(),
+            a.requirements.len()
+        )?;
+        writeln!(w)?;
+        for r in &a.requirements {
+            let mark = if r.status == RequirementStatus::Completed { "x" } else { " " };
+            writeln!(w, "- [{}] {} ({})", mark, r.name, r.progress_description)?;
+        }
+        writeln!(w)?;
+    }
+
+    if !report.skipped.is_empty() {
+        writeln!(w, "## Skipped")?;
+        writeln!(w)?;
+        for s in &report.skipped {
+            writeln!(w, "- `{}` ({}): {}", s.id.to_string(), s.struct_name, s.reason)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        achievements::{
+            AchievementReport, AchievementStatus, Requirement, RequirementStatus, SkippedAchievement,
+        },
+        sii::value::ID,
+    };
+
+    use super::{write_report, ReportFormat};
+
+    fn report() -> Result<AchievementReport> {
+        let requirement = |name: &str, status, done| Requirement {
+            name: name.to_owned(),
+            status,
+            progress_description: format!("{}/2", done),
+            done,
+            required: 2,
+            country: None,
+        };
+        Ok(AchievementReport {
+            achievements: vec![AchievementStatus {
+                id: "test".to_owned(),
+                title: "Say \"cheese\"".to_owned(),
+                description: None,
+                requirements: vec![
+                    requirement("Berlin, DE", RequirementStatus::Completed, 2),
+                    requirement("Dresden", RequirementStatus::Started, 1),
+                ],
+            }],
+            skipped: vec![SkippedAchievement {
+                id: ID::try_from(".achievement.ferry")?,
+                struct_name: "achievement_ferry_data".to_owned(),
+                reason: "not recorded".to_owned(),
+            }],
+        })
+    }
+
+    fn render(format: ReportFormat) -> Result<String> {
+        let mut out = Vec::new();
+        write_report(&mut out, &report()?, format)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn aggregates_progress() -> Result<()> {
+        let a = &report()?.achievements[0];
+        assert_eq!(a.status(), RequirementStatus::Started);
+        assert_eq!(a.completed_count(), 1);
+        assert_eq!(a.percent_complete(), 75.0);
+        Ok(())
+    }
+
+    #[test]
+    fn renders_formats() -> Result<()> {
+        let json = render(ReportFormat::Json)?;
+        assert!(json.starts_with(
+            "{\"achievements\":[{\"id\":\"test\",\"title\":\"Say \\\"cheese\\\"\",\
+             \"description\":null,\"status\":\"started\",\"percent_complete\":75.0,"
+        ));
+        assert!(json.contains("\"skipped\":[{\"id\":\".achievement.ferry\""));
+
+        let csv = render(ReportFormat::Csv)?;
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("test,\"Say \"\"cheese\"\"\",\"Berlin, DE\",completed,2,2,2/2,")
+        );
+
+        let md = render(ReportFormat::Markdown)?;
+        assert!(md.contains("- [ ] Dresden (1/2)"));
+
+        assert!(!render(ReportFormat::Text)?.contains('\x1b'));
+        Ok(())
+    }
+}
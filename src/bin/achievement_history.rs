@@ -0,0 +1,80 @@
+use std::{env, fs, path::Path, time::UNIX_EPOCH};
+
+use anyhow::{Result, anyhow, bail};
+use siirs::{
+    achievements::{history::{self, ProgressStore}, AchievementEvaluator},
+    game::Game,
+    profiles,
+    sii::game::SaveContainer,
+};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        bail!(
+            "Usage: {} <path to history sqlite> <path to game folder> <documents folder or game.sii>...",
+            args[0]
+        );
+    }
+
+    let mut saves = Vec::new();
+    for arg in &args[3..] {
+        let path = Path::new(arg);
+        if path.is_dir() {
+            // Every save slot of the most recently saved profile.
+            let profile = profiles::find_profiles(path)?
+                .into_iter()
+                .find(|p| !p.saves.is_empty())
+                .ok_or_else(|| anyhow!("no saves found in {}", path.display()))?;
+            for slot in profile.saves {
+                saves.push((path_str(&slot.game_sii())?, slot.file_time));
+            }
+        } else {
+            saves.push((arg.clone(), save_time(path)?));
+        }
+    }
+    saves.sort_by_key(|(_, time)| *time);
+
+    let mut store = ProgressStore::open(Path::new(&args[1]))?;
+    let game = Game::detect(Path::new(&args[2]), Path::new(&args[3]))?;
+    let evaluator = AchievementEvaluator::load(&args[2], game, "en_us", &[])?;
+    for (path, e) in evaluator.skipped_files() {
+        eprintln!("Skipping {}: {:#}", path.display(), e);
+    }
+    let evaluated = history::record_history(&mut store, &evaluator, &saves)?;
+    eprintln!("Evaluated {} new of {} saves", evaluated, saves.len());
+
+    for t in store.transitions()? {
+        let from = t.from.map_or("-", |f| f.as_str());
+        println!(
+            "{} {}: {} -> {} ({}: {})",
+            t.save_time,
+            t.achievement,
+            from,
+            t.to.as_str(),
+            t.requirement,
+            t.save
+        );
+    }
+
+    Ok(())
+}
+
+/// The time from the save's `info.sii`, or when the file was written.
+fn save_time(game_sii: &Path) -> Result<u64> {
+    if let Some(info) = game_sii
+        .parent()
+        .and_then(|dir| SaveContainer::load(&dir.join("info.sii")).ok())
+    {
+        return Ok(info.file_time);
+    }
+
+    Ok(fs::metadata(game_sii)?
+        .modified()?
+        .duration_since(UNIX_EPOCH)?
+        .as_secs())
+}
+
+fn path_str(path: &Path) -> Result<String> {
+    Ok(path.to_str().ok_or_else(|| anyhow!("illegal filename"))?.to_owned())
+}
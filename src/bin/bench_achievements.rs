@@ -0,0 +1,60 @@
+use std::{env, path::Path, time::{Duration, Instant}};
+
+use anyhow::{Result, bail};
+use siirs::{
+    achievements::{
+        load_save_data,
+        report::{self, ReportFormat},
+        AchievementEvaluator, Backend,
+    },
+    game::Game,
+};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        bail!(
+            "Usage: {} <path to game.sii> <path to game folder> [<iterations>]",
+            args[0]
+        );
+    }
+    let iterations: u32 = args.get(3).map_or(Ok(5), |n| n.parse())?;
+    if iterations == 0 {
+        bail!("iterations must be at least 1");
+    }
+
+    let game = Game::detect(Path::new(&args[2]), Path::new(&args[1]))?;
+    let evaluator = AchievementEvaluator::load(&args[2], game, "en_us", &[])?;
+    let mut reports = Vec::new();
+    for backend in [Backend::Sqlite, Backend::Index] {
+        let mut load = Duration::ZERO;
+        let mut eval = Duration::ZERO;
+        let mut rendered = Vec::new();
+        for _ in 0..iterations {
+            let start = Instant::now();
+            let save_data = load_save_data(&args[1], backend)?;
+            let loaded = Instant::now();
+            let report = evaluator.evaluate_data(&save_data)?;
+            eval += loaded.elapsed();
+            load += loaded - start;
+
+            rendered.clear();
+            report::write_report(&mut rendered, &report, ReportFormat::Json)?;
+        }
+
+        println!(
+            "{:?}: load {:.1?}, evaluate {:.1?} (mean of {})",
+            backend,
+            load / iterations,
+            eval / iterations,
+            iterations
+        );
+        reports.push(rendered);
+    }
+
+    if reports[0] != reports[1] {
+        bail!("the SQLite and index backends disagree");
+    }
+    println!("Reports are identical");
+    Ok(())
+}
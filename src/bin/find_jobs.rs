@@ -0,0 +1,52 @@
+use std::{env, path::Path};
+
+use anyhow::{bail, Result};
+use siirs::{achievements::AchievementEvaluator, game::Game};
+
+fn main() -> Result<()> {
+    // --game=<ets2|ats> may appear anywhere; by default it is detected from
+    // the game folder and the save.
+    let mut game = None;
+    let mut args: Vec<String> = Vec::new();
+    for arg in env::args() {
+        if let Some(g) = arg.strip_prefix("--game=") {
+            game = Some(g.parse()?);
+        } else {
+            args.push(arg);
+        }
+    }
+    if args.len() != 3 && args.len() != 4 {
+        bail!(
+            "Usage: {} [--game=ets2|ats] <path to game.sii> <path to game folder> [<language, e.g. de_de>]",
+            args[0]
+        );
+    }
+
+    let lang = args.get(3).map_or("en_us", String::as_str);
+    let game = match game {
+        Some(game) => game,
+        None => Game::detect(Path::new(&args[2]), Path::new(&args[1]))?,
+    };
+    let evaluator = AchievementEvaluator::load(&args[2], game, lang, &[])?;
+    for (path, e) in evaluator.skipped_files() {
+        eprintln!("Skipping {}: {:#}", path.display(), e);
+    }
+    let jobs = evaluator.find_jobs(&args[1])?;
+    if jobs.is_empty() {
+        println!("No open jobs would complete an achievement requirement.");
+    }
+
+    for job in jobs {
+        println!(
+            "{} ({}): {:?} -> {:?}, {} ({})",
+            job.achievement,
+            job.requirement,
+            job.source,
+            job.offer.target.as_ref().expect("open jobs have a target"),
+            job.offer.cargo_name().unwrap_or_default(),
+            game.format_distance(job.offer.distance_km as f64),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::{env, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use siirs::{
+    game::Game,
+    sii::{
+        game::{Fleet, FromGameSave, GameSave, SaveSummary},
+        value::ID,
+    },
+};
+
+fn main() -> Result<()> {
+    // --game=<ets2|ats> may appear anywhere; by default it is detected from
+    // the save's profile or documents folder.
+    let mut game = None;
+    let mut args: Vec<String> = Vec::new();
+    for arg in env::args() {
+        if let Some(g) = arg.strip_prefix("--game=") {
+            game = Some(g.parse()?);
+        } else {
+            args.push(arg);
+        }
+    }
+    if args.len() != 2 {
+        bail!("Usage: {} [--game=ets2|ats] <path to game.sii>", args[0]);
+    }
+    let game: Game = game
+        .or_else(|| Game::detect_save(Path::new(&args[1])))
+        .ok_or_else(|| anyhow!("cannot tell whether {} is ETS2 or ATS; pass --game", args[1]))?;
+
+    let save = GameSave::new_from_save(&args[1])?;
+    let fleet = Fleet::from_game_save(&save)?;
+    let city = |id: &Option<ID>| {
+        id.as_ref().and_then(|g| g.string_part(-1)).unwrap_or_else(|| "-".to_owned())
+    };
+
+    println!("{}:", game.name());
+    for (label, value) in SaveSummary::from_game_save(&save)?.describe(game) {
+        println!("  {:<16} {}", label, value);
+    }
+
+    println!("Trucks:");
+    for t in &fleet.trucks {
+        let model = t
+            .model
+            .as_ref()
+            .map_or_else(|| "?".to_owned(), |m| format!("{} {}", m.brand, m.model));
+        let driver = t.driver.as_ref().map_or_else(|| "-".to_owned(), |d| format!("{d:?}"));
+        println!(
+            "  {:<12} {:<24} {:>12}  engine {:>3.0}%  fuel {:>3.0}%  garage {:<12} driver {}",
+            t.license_plate,
+            model,
+            game.format_distance(t.odometer_km()),
+            t.wear.engine * 100.0,
+            t.fuel_relative * 100.0,
+            city(&t.garage),
+            driver,
+        );
+    }
+
+    println!("Drivers:");
+    for d in &fleet.drivers {
+        println!(
+            "  {:?}  xp {:>7}  garage {:<12} revenue {:>9}  profit {:>9}  {}",
+            d.id,
+            d.experience_points,
+            city(&d.garage),
+            game.format_money(d.earnings.revenue),
+            game.format_money(d.earnings.profit()),
+            game.format_distance(d.earnings.distance as f64),
+        );
+    }
+
+    println!("Trailers: {}, garages: {}", fleet.trailers.len(), fleet.garages.len());
+    Ok(())
+}
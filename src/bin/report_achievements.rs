@@ -1,41 +1,77 @@
-use std::env;
+use std::{env, io, path::{Path, PathBuf}};
 
-use anyhow::{Result, bail};
-use siirs::achievements::{self, AchievementStatus, RequirementStatus};
+use anyhow::{Result, anyhow, bail};
+use siirs::{
+    achievements::{report::{self, ReportFormat}, AchievementEvaluator, AchievementRegistry},
+    game::Game,
+    profiles,
+};
 
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    // --format=<text|json|csv|markdown>, --lang=<code>, --goals=<sii> and
+    // --game=<ets2|ats> may appear anywhere.
+    let mut format = ReportFormat::Text;
+    let mut lang = "en_us".to_owned();
+    let mut game = None;
+    let mut goals = Vec::new();
+    let mut args: Vec<String> = Vec::new();
+    for arg in env::args() {
+        if let Some(f) = arg.strip_prefix("--format=") {
+            format = f.parse()?;
+        } else if let Some(l) = arg.strip_prefix("--lang=") {
+            lang = l.to_owned();
+        } else if let Some(g) = arg.strip_prefix("--goals=") {
+            goals.push(PathBuf::from(g));
+        } else if let Some(g) = arg.strip_prefix("--game=") {
+            game = Some(g.parse()?);
+        } else {
+            args.push(arg);
+        }
+    }
     if args.len() != 3 && args.len() != 4 {
-        bail!("Usage: {} <path to game.sii> <path to game folder> [<achievement_id>]", args[0]);
+        bail!(
+            "Usage: {} [--format=text|json|csv|markdown] [--lang=en_us] [--goals=<custom goals sii>] [--game=ets2|ats] <path to game.sii or documents folder> <path to game folder> [<achievement_id>]",
+            args[0]
+        );
     }
 
-    let results = achievements::get_achievement_status(&args[1], &args[2])?;
-    for status in results {
-        if args.len() == 3 || &args[3] == &status.name {
-            print_results(&status);
-        }
-    }
+    // Given a documents folder, use the latest autosave and the profile's mods.
+    let documents = Path::new(&args[1]);
+    let (save_path, mods) = if documents.is_dir() {
+        let (profile, slot) = profiles::find_profiles(documents)?
+            .into_iter()
+            .find_map(|p| p.latest_autosave().cloned().map(|s| (p, s)))
+            .ok_or_else(|| anyhow!("no autosaves found in {}", documents.display()))?;
+        eprintln!("Using {}", slot.game_sii().display());
+        let save_path = slot
+            .game_sii()
+            .to_str()
+            .ok_or_else(|| anyhow!("illegal filename"))?
+            .to_owned();
+        (save_path, profile.mod_archives(documents))
+    } else {
+        (args[1].clone(), Vec::new())
+    };
 
-    Ok(())
-}
-
-fn print_results(status: &AchievementStatus) {
-    print_boxed(&status.name);
-    for req in &status.requirements {
-        let prefix = if req.status == RequirementStatus::Completed {
-            "\x1b[1;32m✓\x1b[1;30m "
-        } else {
-            "\x1b[0m  "
-        };
+    // The game folder decides which achievements there are.
+    let game = match game {
+        Some(game) => game,
+        None => Game::detect(Path::new(&args[2]), documents)?,
+    };
+    let registry = AchievementRegistry::for_game(game);
+    let mut evaluator = AchievementEvaluator::load_with(&args[2], &lang, &mods, &registry)?;
+    for (path, e) in evaluator.skipped_files() {
+        eprintln!("Skipping {}: {:#}", path.display(), e);
+    }
+    for path in &goals {
+        evaluator.add_goals(path, &registry)?;
+    }
 
-        println!("{} {}: {}", prefix, req.progress_description, req.name);
+    let mut report = evaluator.evaluate(&save_path)?;
+    if let Some(id) = args.get(3) {
+        report.achievements.retain(|status| &status.id == id);
+        report.skipped.clear();
     }
 
-    println!("\x1b[0m")
+    report::write_report(&mut io::stdout().lock(), &report, format)
 }
-
-fn print_boxed(s: &str) {
-    println!("╭─{}─╮", "─".repeat(s.len()));
-    println!("│ {} │", s);
-    println!("╰─{}─╯", "─".repeat(s.len()));
-}
\ No newline at end of file
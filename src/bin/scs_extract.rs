@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use glob::{MatchOptions, Pattern};
+use siirs::scs::{Archive, Entry};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("list") if args.len() == 3 => list(&args[2]),
+        Some("extract") if args.len() == 4 || args.len() == 5 => {
+            extract(&args[2], &args[3], args.get(4).map(String::as_str))
+        }
+        _ => bail!(
+            "Usage: {0} list <path to scs>\n       {0} extract <path to scs> <output dir> [<glob>]",
+            args[0]
+        ),
+    }
+}
+
+/// Resolves a name for every entry in the archive, sorted by name.  Entries
+/// that are not reachable from the root directory are named
+/// `_unknown/<hash>`.
+fn named_entries(archive: &Archive) -> Result<Vec<(String, &Entry)>> {
+    let mut paths: HashMap<u64, String> = archive.resolve_paths()?;
+    let mut entries = archive
+        .entries()
+        .map(|e| {
+            let name = paths
+                .remove(&e.hash)
+                .unwrap_or_else(|| format!("_unknown/{:016x}", e.hash));
+            (name, e)
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+fn list(scs_path: &str) -> Result<()> {
+    let archive = Archive::load_from_path(scs_path)?;
+    let mut total_size = 0u64;
+    let mut total_zsize = 0u64;
+
+    println!("{:>10} {:>10} {:>6} {:>8}  path", "size", "stored", "ratio", "crc32");
+    for (name, e) in named_entries(&archive)? {
+        if e.entry_type.is_dir() {
+            continue;
+        }
+
+        let stored = if e.entry_type.is_compressed() { e.zsize } else { e.size };
+        total_size += e.size as u64;
+        total_zsize += stored as u64;
+        println!(
+            "{:>10} {:>10} {:>6} {:08x}  {}",
+            e.size,
+            stored,
+            ratio(e.size as u64, stored as u64),
+            e.crc32,
+            name
+        );
+    }
+
+    println!(
+        "{:>10} {:>10} {:>6}",
+        total_size,
+        total_zsize,
+        ratio(total_size, total_zsize)
+    );
+    Ok(())
+}
+
+fn ratio(size: u64, stored: u64) -> String {
+    if size == 0 {
+        "-".to_owned()
+    } else {
+        format!("{:.1}%", stored as f64 * 100.0 / size as f64)
+    }
+}
+
+fn extract(scs_path: &str, out_dir: &str, pattern: Option<&str>) -> Result<()> {
+    let pattern = pattern.map(Pattern::new).transpose()?;
+    let match_options = MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+
+    let archive = Archive::load_from_path(scs_path)?;
+    let wanted = named_entries(&archive)?
+        .into_iter()
+        .filter(|(name, e)| {
+            !e.entry_type.is_dir()
+                && pattern
+                    .as_ref()
+                    .is_none_or(|p| p.matches_with(name, match_options))
+        })
+        .map(|(name, e)| (name, e.hash))
+        .collect::<Vec<_>>();
+
+    for (name, hash) in wanted {
+        let out_path = output_path(out_dir, &name)?;
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut reader = archive.open_entry(hash)?.with_crc32_check();
+        let mut out_file = File::create(&out_path)?;
+        std::io::copy(&mut reader, &mut out_file).with_context(|| format!("extracting {name}"))?;
+        println!("{name}");
+    }
+
+    Ok(())
+}
+
+// Names come from inside the archive, so don't let them escape the output
+// directory.
+fn output_path(out_dir: &str, name: &str) -> Result<PathBuf> {
+    let rel = Path::new(name);
+    if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        bail!("refusing to extract entry with unsafe path '{name}'");
+    }
+
+    Ok(Path::new(out_dir).join(rel))
+}
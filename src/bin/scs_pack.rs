@@ -0,0 +1,31 @@
+use std::{
+    env,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Result};
+use siirs::scs::writer::ArchiveWriter;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let compress = match args.get(3).map(String::as_str) {
+        _ if args.len() == 3 => true,
+        Some("--store") if args.len() == 4 => false,
+        _ => bail!("Usage: {} <input dir> <path to output scs> [--store]", args[0]),
+    };
+
+    let mut writer = ArchiveWriter::new(compress);
+    writer.add_dir(Path::new(&args[1]))?;
+
+    let out_file = File::options()
+        .create_new(true)
+        .write(true)
+        .open(&args[2])?;
+    let mut out = BufWriter::new(out_file);
+    writer.write(&mut out)?;
+    out.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::env;
+
+use anyhow::{bail, Result};
+use siirs::sii::{
+    diff::{diff_saves, format_value, Delta, FieldChange},
+    game::GameSave,
+};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 {
+        bail!("Usage: {} <path to old save> <path to new save>", args[0]);
+    }
+
+    let old = GameSave::new_from_save(&args[1])?;
+    let new = GameSave::new_from_save(&args[2])?;
+    let diff = diff_saves(&old, &new);
+
+    for (id, struct_name) in &diff.removed {
+        println!("- {struct_name} {id:?}");
+    }
+    for (id, struct_name) in &diff.added {
+        println!("+ {struct_name} {id:?}");
+    }
+    for (id, struct_name) in &diff.unmatched_old {
+        println!("? old {struct_name} {id:?} (no match by referrer)");
+    }
+    for (id, struct_name) in &diff.unmatched_new {
+        println!("? new {struct_name} {id:?} (no match by referrer)");
+    }
+
+    for block in &diff.changed {
+        match &block.old_struct_name {
+            Some(old_name) => println!("~ {} {:?} (was {old_name})", block.struct_name, block.id),
+            None => println!("~ {} {:?}", block.struct_name, block.id),
+        }
+
+        for f in &block.fields {
+            let change = match &f.change {
+                FieldChange::Value { old, new, delta } => {
+                    let delta = match delta {
+                        Some(Delta::Integer(d)) => format!(" ({d:+})"),
+                        Some(Delta::Float(d)) => format!(" ({d:+})"),
+                        None => String::new(),
+                    };
+                    format!("{} -> {}{delta}", format_value(old), format_value(new))
+                }
+                FieldChange::Set { added, removed } => added
+                    .iter()
+                    .map(|e| format!("+{e}"))
+                    .chain(removed.iter().map(|e| format!("-{e}")))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                FieldChange::Reordered => "reordered".to_owned(),
+                FieldChange::Added(v) => format!("added {}", format_value(v)),
+                FieldChange::Removed(v) => format!("removed {}", format_value(v)),
+            };
+            println!("    {}: {change}", f.field);
+        }
+    }
+
+    Ok(())
+}
@@ -1,11 +1,24 @@
 
-use std::io::Read;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    io::{Read, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
 use anyhow::{anyhow, bail, Result};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+
+const SII_ENCRYPTED_SIGNATURE: u32 = 0x43736353;
+const SII_AES_KEY: [u8; 32] = [
+    0x2A, 0x5F, 0xCB, 0x17, 0x91, 0xD2, 0x2F, 0xB6, 0x02, 0x45, 0xB3, 0xD8, 0x36, 0x9E, 0xD0,
+    0xB2, 0xC2, 0x73, 0x71, 0x56, 0x3F, 0xBF, 0x1F, 0x3C, 0x9E, 0xDF, 0x6B, 0x11, 0x82, 0x5A,
+    0x5D, 0x0A,
+];
 
 pub struct Decryptor<R: Read> {
     reader: R,
@@ -18,13 +31,6 @@ impl<R> Decryptor<R>
 where
     R: Read,
 {
-    const SII_ENCRYPTED_SIGNATURE: u32 = 0x43736353;
-    const SII_AES_KEY: [u8; 32] = [
-        0x2A, 0x5F, 0xCB, 0x17, 0x91, 0xD2, 0x2F, 0xB6, 0x02, 0x45, 0xB3, 0xD8, 0x36, 0x9E, 0xD0,
-        0xB2, 0xC2, 0x73, 0x71, 0x56, 0x3F, 0xBF, 0x1F, 0x3C, 0x9E, 0xDF, 0x6B, 0x11, 0x82, 0x5A,
-        0x5D, 0x0A,
-    ];
-
     pub fn new(reader: R) -> Self {
         Self { reader }
     }
@@ -34,9 +40,11 @@ where
         let mut buf = Vec::new();
         self.reader.read_to_end(&mut buf)?;
 
-        Aes256CbcDec::new(&Self::SII_AES_KEY.into(), &iv.into())
+        let len = Aes256CbcDec::new(&SII_AES_KEY.into(), &iv.into())
             .decrypt_padded_mut::<Pkcs7>(&mut buf)
-            .map_err(|e| anyhow!("decryption failed: {e}"))?;
+            .map_err(|e| anyhow!("decryption failed: {e}"))?
+            .len();
+        buf.truncate(len);
 
         Ok(buf)
     }
@@ -46,7 +54,7 @@ where
         let mut iv: [u8; 16] = [0; 16];
 
         let signature = self.reader.read_u32::<LittleEndian>()?;
-        if signature != Self::SII_ENCRYPTED_SIGNATURE {
+        if signature != SII_ENCRYPTED_SIGNATURE {
             bail!("invalid signature: {signature:X}")
         }
 
@@ -57,3 +65,79 @@ where
         Ok(iv)
     }
 }
+
+pub struct Encryptor<W: Write> {
+    writer: W,
+}
+
+impl<W> Encryptor<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Encrypts `data`, which is normally a zlib-compressed binary sii file.
+    /// `plain_len` is the size of the data before compression, which the
+    /// header records.
+    ///
+    /// The HMAC in the header is left zeroed since its key is unknown, so
+    /// whether the game accepts the result is untested.
+    pub fn encrypt(mut self, data: &[u8], plain_len: u32) -> Result<()> {
+        let iv = random_iv();
+        let mut buf = vec![0u8; data.len() + 16];
+        buf[..data.len()].copy_from_slice(data);
+        let encrypted = Aes256CbcEnc::new(&SII_AES_KEY.into(), &iv.into())
+            .encrypt_padded_mut::<Pkcs7>(&mut buf, data.len())
+            .map_err(|e| anyhow!("encryption failed: {e}"))?;
+
+        self.writer.write_u32::<LittleEndian>(SII_ENCRYPTED_SIGNATURE)?;
+        self.writer.write_all(&[0; 32])?;
+        self.writer.write_all(&iv)?;
+        self.writer.write_u32::<LittleEndian>(plain_len)?;
+        self.writer.write_all(encrypted)?;
+        Ok(())
+    }
+}
+
+/// A fresh IV per file.  Without a rand dependency, this hashes the time
+/// with std's randomly keyed hasher; that is not cryptographically secure, but
+/// the key is public anyway.
+fn random_iv() -> [u8; 16] {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let mut iv = [0u8; 16];
+    for half in iv.chunks_exact_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    iv
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::{Decryptor, Encryptor};
+
+    #[test]
+    fn round_trip_encryption() -> Result<()> {
+        for data in [&b""[..], b"0123456789abcdef", b"BSII and then some"] {
+            let mut out = Vec::new();
+            Encryptor::new(&mut out).encrypt(data, 1234)?;
+            assert_eq!(&out[52..56], &1234u32.to_le_bytes());
+            assert_eq!(Decryptor::new(out.as_slice()).decrypt()?, data);
+        }
+
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        Encryptor::new(&mut first).encrypt(b"same", 4)?;
+        Encryptor::new(&mut second).encrypt(b"same", 4)?;
+        assert_ne!(&first[36..52], &second[36..52]);
+
+        Ok(())
+    }
+}
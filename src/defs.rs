@@ -0,0 +1,307 @@
+//! Game definition data from the `def/` tree: cities, companies, cargo and
+//! countries.
+//!
+//! Definitions are spread over `def.scs` and the DLC archives, mostly as
+//! `.sui` files included from `def/city.sii` and friends.  Rather than follow
+//! the includes, every file directly under `def/city`, `def/company`,
+//! `def/cargo` and `def/country` is read; later archives override earlier
+//! ones.
+
+use std::{collections::BTreeMap, io::Read, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    locale::LocaleDB,
+    scs::{self, Archive},
+    sii::{format, game::FromStruct, value::Struct},
+};
+
+const DEF_DIRS: [&str; 4] = ["def/city", "def/company", "def/cargo", "def/country"];
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "city_data")]
+pub struct CityDef {
+    #[sii(with = "token")]
+    pub token: String,
+    /// Localized, falling back to `city_name`.
+    #[sii(with = "city_name")]
+    pub name: Option<String>,
+    #[sii(optional)]
+    pub short_city_name: Option<String>,
+    /// Token of the country, e.g. `germany`.
+    #[sii(optional)]
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "company_permanent")]
+pub struct CompanyDef {
+    #[sii(with = "token")]
+    pub token: String,
+    #[sii(optional)]
+    pub name: Option<String>,
+    #[sii(optional)]
+    pub sort_name: Option<String>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "cargo_data")]
+pub struct CargoDef {
+    #[sii(with = "token")]
+    pub token: String,
+    #[sii(optional)]
+    pub name: Option<String>,
+    #[sii(optional)]
+    pub fragility: Option<f32>,
+    /// Cargo groups, e.g. `refrigerated` or `adr`.
+    #[sii(rename = "group", default)]
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "country_data")]
+pub struct CountryDef {
+    #[sii(with = "token")]
+    pub token: String,
+    /// Localized, falling back to `name`.
+    #[sii(with = "country_name")]
+    pub name: Option<String>,
+    #[sii(optional)]
+    pub country_code: Option<String>,
+}
+
+/// The last part of a def's ID, which is how everything else refers to it:
+/// `city.berlin` is `berlin`.
+fn token(value: &Struct) -> Result<String> {
+    let id = value.id.to_string();
+    id.rsplit('.')
+        .next()
+        .filter(|t| !t.is_empty())
+        .map(str::to_owned)
+        .ok_or_else(|| anyhow!("def {} has no name", id))
+}
+
+fn city_name(value: &Struct) -> Result<Option<String>> {
+    match value.get_opt_as("city_name_localized")? {
+        Some(name) => Ok(Some(name)),
+        None => value.get_opt_as("city_name"),
+    }
+}
+
+fn country_name(value: &Struct) -> Result<Option<String>> {
+    match value.get_opt_as("name_localized")? {
+        Some(name) => Ok(Some(name)),
+        None => value.get_opt_as("name"),
+    }
+}
+
+/// Everything loaded from the def files, by token.  A city or country whose
+/// localized name refers to a locale key `LocaleDB` does not know keeps its
+/// unlocalized name; a company or cargo has none to fall back to, so its
+/// `name` is `None` then.
+#[derive(Debug, Default)]
+pub struct GameDefs {
+    pub cities: BTreeMap<String, CityDef>,
+    pub companies: BTreeMap<String, CompanyDef>,
+    pub cargos: BTreeMap<String, CargoDef>,
+    pub countries: BTreeMap<String, CountryDef>,
+    /// The DLC archives and def files `load` left out, with the reason for
+    /// each.
+    pub skipped: scs::Skipped,
+}
+
+impl GameDefs {
+    /// Loads the defs from `def.scs` and the DLC archives in the game folder,
+    /// resolving names through `ldb`.  Only `def.scs` has to be readable; DLC
+    /// archives and def files that are not are listed in `skipped`.
+    pub fn load(game_path: &Path, ldb: &LocaleDB) -> Result<Self> {
+        let (archives, skipped) =
+            scs::open_archives(&game_path.join("def.scs"), scs::dlc_archives(game_path)?)?;
+        let mut defs = Self { skipped, ..Self::default() };
+        for (archive_path, archive) in archives {
+            for dir in DEF_DIRS {
+                let Ok(listing) = archive.read_dir(dir) else {
+                    continue;
+                };
+                for entry in listing.iter().filter(|e| !e.is_dir) {
+                    // The text parser does not cover every def file yet; one
+                    // we cannot read only costs us its names.
+                    let path = format!("{}/{}", dir, entry.name);
+                    match read_def_file(&archive, &path) {
+                        Ok(structs) => defs.add_structs(&structs, ldb),
+                        Err(e) => defs.skipped.push((archive_path.join(path), e)),
+                    }
+                }
+            }
+        }
+
+        Ok(defs)
+    }
+
+    /// Adds every city, company, cargo or country def in `structs`, skipping
+    /// anything else and defs that do not decode.
+    pub fn add_structs(&mut self, structs: &[Struct], ldb: &LocaleDB) {
+        let localize = |name: &mut Option<String>, raw: Option<String>| {
+            *name = name.take().and_then(|n| ldb.resolve(&n)).or(raw);
+        };
+        let raw = |s: &Struct, field| s.get_opt_as(field).ok().flatten();
+
+        for s in structs {
+            match s.struct_name.as_str() {
+                "city_data" => {
+                    if let Ok(mut city) = CityDef::try_from(s) {
+                        localize(&mut city.name, raw(s, "city_name"));
+                        self.cities.insert(city.token.clone(), city);
+                    }
+                }
+                "company_permanent" => {
+                    if let Ok(mut company) = CompanyDef::try_from(s) {
+                        localize(&mut company.name, None);
+                        self.companies.insert(company.token.clone(), company);
+                    }
+                }
+                "cargo_data" => {
+                    if let Ok(mut cargo) = CargoDef::try_from(s) {
+                        localize(&mut cargo.name, None);
+                        self.cargos.insert(cargo.token.clone(), cargo);
+                    }
+                }
+                "country_data" => {
+                    if let Ok(mut country) = CountryDef::try_from(s) {
+                        localize(&mut country.name, raw(s, "name"));
+                        self.countries.insert(country.token.clone(), country);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn country_of_city(&self, city: &str) -> Option<&CountryDef> {
+        let country = self.cities.get(city)?.country.as_ref()?;
+        self.countries.get(country)
+    }
+}
+
+fn read_def_file(archive: &Archive, path: &str) -> Result<Vec<Struct>> {
+    let mut data = Vec::new();
+    archive.open_path(path)?.read_to_end(&mut data)?;
+    if path.ends_with(".sui") {
+        format::read_fragment_structs(&data)
+    } else {
+        format::read_structs(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use std::fs;
+
+    use crate::{
+        locale::LocaleDB,
+        sii::format,
+        test_util::{temp_dir, write_archive},
+    };
+
+    use super::GameDefs;
+
+    #[test]
+    fn skips_unreadable_dlc_archives() -> Result<()> {
+        let dir = temp_dir("defs")?;
+        write_archive(
+            &dir.join("def.scs"),
+            &[
+                ("def/city/berlin.sui", b"city_data: city.berlin\n{\n city_name: \"Berlin\"\n}\n".to_vec()),
+                ("def/city/garbled.sui", b"city_data: city.garbled\n{\n city_name: {\n}\n".to_vec()),
+            ],
+        )?;
+        fs::write(dir.join("dlc_broken.scs"), b"not an archive")?;
+
+        let defs = GameDefs::load(&dir, &LocaleDB::new_empty());
+        fs::remove_file(dir.join("def.scs"))?;
+        let missing_def = GameDefs::load(&dir, &LocaleDB::new_empty());
+        fs::remove_dir_all(&dir)?;
+
+        let defs = defs?;
+        assert_eq!(defs.cities["berlin"].name.as_deref(), Some("Berlin"));
+        let skipped: Vec<_> = defs.skipped.iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(
+            skipped,
+            [dir.join("dlc_broken.scs"), dir.join("def.scs/def/city/garbled.sui")]
+        );
+        assert!(missing_def.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn loads_localized_defs() -> Result<()> {
+        let ldb = LocaleDB::new_from_reader(
+            &mut &b"SiiNunit
+{
+localization_db : .localization
+{
+ key[]: \"berlin\"
+ val[]: \"Berlin\"
+ key[]: \"germany\"
+ val[]: \"Deutschland\"
+}
+}
+"[..],
+        )?;
+        let structs = format::read_fragment_structs(
+            b"city_data: city.hamburg
+{
+ city_name: \"Hamburg\"
+ city_name_localized: \"@@hamburg@@\"
+}
+country_data: country.data.austria
+{
+ name: \"Austria\"
+ name_localized: \"@@austria@@\"
+}
+city_data: city.berlin
+{
+ city_name: \"Berlin (unlocalized)\"
+ city_name_localized: \"@@berlin@@\"
+ country: germany
+}
+country_data: country.data.germany
+{
+ name: \"Germany\"
+ name_localized: \"@@germany@@\"
+ country_code: \"DE\"
+ country_id: 9
+}
+company_permanent: company.permanent.posped
+{
+ name: \"Posped\"
+}
+cargo_data: cargo.apples
+{
+ name: \"@@cn_apples@@\"
+ fragility: 0.3
+ group[]: refrigerated
+}
+",
+        )?;
+
+        let mut defs = GameDefs::default();
+        defs.add_structs(&structs, &ldb);
+        assert_eq!(defs.cities["berlin"].name.as_deref(), Some("Berlin"));
+        assert_eq!(
+            defs.country_of_city("berlin").and_then(|c| c.name.as_deref()),
+            Some("Deutschland")
+        );
+        assert_eq!(defs.companies["posped"].name.as_deref(), Some("Posped"));
+        // Unknown keys fall back to the unlocalized name where there is one.
+        assert_eq!(defs.cities["hamburg"].name.as_deref(), Some("Hamburg"));
+        assert_eq!(defs.countries["austria"].name.as_deref(), Some("Austria"));
+        // Otherwise they are left for the caller's fallback.
+        assert_eq!(defs.cargos["apples"].name, None);
+        assert_eq!(defs.cargos["apples"].groups, ["refrigerated"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+//! Telling Euro Truck Simulator 2 and American Truck Simulator apart.
+//!
+//! Both games share the save and archive formats, but not their units: ATS
+//! shows distances in miles and money in dollars.  Saves and def files store
+//! km either way, so conversion only happens for display.
+
+use std::{
+    fmt,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+
+use crate::sii::game::UserProfile;
+
+const KM_PER_MILE: f64 = 1.609344;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Game {
+    #[default]
+    Ets2,
+    Ats,
+}
+
+impl Game {
+    pub fn name(self) -> &'static str {
+        match self {
+            Game::Ets2 => "Euro Truck Simulator 2",
+            Game::Ats => "American Truck Simulator",
+        }
+    }
+
+    /// `map_path` of profiles on the base game's map.
+    pub fn map_path(self) -> &'static str {
+        match self {
+            Game::Ets2 => "/map/europe.mbd",
+            Game::Ats => "/map/usa.mbd",
+        }
+    }
+
+    /// The game executable's name in `bin/<platform>/`, without extension.
+    fn executable(self) -> &'static str {
+        match self {
+            Game::Ets2 => "eurotrucks2",
+            Game::Ats => "amtrucks",
+        }
+    }
+
+    /// The game of the install folder and the save at `save_path`.  Fails if
+    /// neither can be recognized or they disagree, rather than guess.
+    pub fn detect(game_path: &Path, save_path: &Path) -> Result<Self> {
+        match (Self::detect_install(game_path), Self::detect_save(save_path)) {
+            (Some(install), Some(save)) if install != save => bail!(
+                "{} is a {} install, but {} is a {} save",
+                game_path.display(),
+                install.name(),
+                save_path.display(),
+                save.name()
+            ),
+            (Some(game), _) | (None, Some(game)) => Ok(game),
+            (None, None) => bail!(
+                "cannot tell whether {} and {} are ETS2 or ATS",
+                game_path.display(),
+                save_path.display()
+            ),
+        }
+    }
+
+    pub fn from_map_path(map_path: &str) -> Option<Self> {
+        [Game::Ets2, Game::Ats].into_iter().find(|g| g.map_path() == map_path)
+    }
+
+    /// Detects the game from an install folder, by its executable or else the
+    /// folder's name.
+    pub fn detect_install(game_path: &Path) -> Option<Self> {
+        if let Ok(platforms) = fs::read_dir(game_path.join("bin")) {
+            for platform in platforms.flatten() {
+                let Ok(files) = fs::read_dir(platform.path()) else {
+                    continue;
+                };
+                for file in files.flatten() {
+                    let path = file.path();
+                    let stem = path.file_stem().and_then(|s| s.to_str());
+                    if let Some(game) = [Game::Ets2, Game::Ats]
+                        .into_iter()
+                        .find(|g| stem == Some(g.executable()))
+                    {
+                        return Some(game);
+                    }
+                }
+            }
+        }
+
+        Self::from_dir_name(game_path)
+    }
+
+    /// Detects the game from a save, a profile or the documents folder: by
+    /// the `map_path` of the nearest `profile.sii`, or else the name of the
+    /// documents folder.  Mod maps with their own `map_path` are only
+    /// recognized by the folder name.
+    pub fn detect_save(path: &Path) -> Option<Self> {
+        for dir in path.ancestors() {
+            let profile_sii = dir.join("profile.sii");
+            if profile_sii.is_file() {
+                if let Some(game) = UserProfile::load(&profile_sii)
+                    .ok()
+                    .and_then(|p| Self::from_map_path(&p.map_path))
+                {
+                    return Some(game);
+                }
+            }
+        }
+
+        path.ancestors().find_map(Self::from_dir_name)
+    }
+
+    /// The install and documents folders are both named after the game.
+    fn from_dir_name(dir: &Path) -> Option<Self> {
+        let name = dir.file_name()?.to_str()?;
+        [Game::Ets2, Game::Ats].into_iter().find(|g| g.name() == name)
+    }
+
+    /// Unit distances are shown in.
+    pub fn distance_unit(self) -> &'static str {
+        match self {
+            Game::Ets2 => "km",
+            Game::Ats => "mi",
+        }
+    }
+
+    /// Converts km to the game's distance unit.
+    pub fn distance(self, km: f64) -> f64 {
+        match self {
+            Game::Ets2 => km,
+            Game::Ats => km / KM_PER_MILE,
+        }
+    }
+
+    /// E.g. `380 km` or `236 mi`.
+    pub fn format_distance(self, km: f64) -> String {
+        format!("{:.0} {}", self.distance(km), self.distance_unit())
+    }
+
+    pub fn currency_symbol(self) -> &'static str {
+        match self {
+            Game::Ets2 => "€",
+            Game::Ats => "$",
+        }
+    }
+
+    /// E.g. `€1500` or `-$20`.
+    pub fn format_money(self, amount: i64) -> String {
+        let sign = if amount < 0 { "-" } else { "" };
+        format!("{}{}{}", sign, self.currency_symbol(), amount.unsigned_abs())
+    }
+}
+
+impl FromStr for Game {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "ets2" | "ets" => Game::Ets2,
+            "ats" => Game::Ats,
+            _ => bail!("unknown game {:?} (ets2, ats)", s),
+        })
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Game::Ets2 => "ets2",
+            Game::Ats => "ats",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+
+    use crate::test_util::temp_path;
+
+    use super::Game;
+
+    #[test]
+    fn detects_game_from_install_and_documents() -> Result<()> {
+        let dir = temp_path("game");
+        let install = dir.join("steamapps/common/some_install");
+        fs::create_dir_all(install.join("bin/linux_x64"))?;
+        fs::write(install.join("bin/linux_x64/amtrucks"), b"")?;
+        let slot = dir.join("Euro Truck Simulator 2/profiles/4A6F/save/autosave");
+        fs::create_dir_all(&slot)?;
+        let documents = dir.join("Documents");
+        fs::create_dir_all(documents.join("profiles/4A6F"))?;
+        fs::write(
+            documents.join("profiles/4A6F/profile.sii"),
+            b"SiiNunit
+{
+user_profile : x.profile
+{
+ profile_name: \"Jo\"
+ company_name: \"Jo Haulage\"
+ face: 0
+ brand: peterbilt
+ logo: logo_1
+ male: true
+ map_path: \"/map/usa.mbd\"
+ cached_experience: 0
+ cached_distance: 0
+ version: 5
+ creation_time: 0
+ save_time: 0
+}
+}
+",
+        )?;
+
+        let from_install = Game::detect_install(&install);
+        let from_folder = Game::detect_save(&slot.join("game.sii"));
+        let from_profile = Game::detect_save(&documents.join("profiles/4A6F/save/1/game.sii"));
+        let unknown = Game::detect_install(&dir);
+        let agreeing = Game::detect(&install, &documents.join("profiles/4A6F/save/1/game.sii"));
+        let conflicting = Game::detect(&install, &slot.join("game.sii"));
+        let unrecognized = Game::detect(&dir, &dir.join("game.sii"));
+        fs::remove_dir_all(&dir)?;
+
+        assert_eq!(from_install, Some(Game::Ats));
+        assert_eq!(from_folder, Some(Game::Ets2));
+        assert_eq!(from_profile, Some(Game::Ats));
+        assert_eq!(unknown, None);
+        assert_eq!(agreeing?, Game::Ats);
+        assert!(conflicting.is_err());
+        assert!(unrecognized.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn formats_units_per_game() -> Result<()> {
+        assert_eq!(Game::Ets2.format_distance(380.0), "380 km");
+        assert_eq!(Game::Ats.format_distance(380.0), "236 mi");
+        assert_eq!(Game::Ats.format_money(-20), "-$20");
+        assert_eq!(Game::Ets2.format_money(1500), "€1500");
+        assert_eq!("ATS".parse::<Game>()?, Game::Ats);
+        Ok(())
+    }
+}
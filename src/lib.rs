@@ -1,5 +1,15 @@
+// Lets code generated by siirs-derive refer to `::siirs` from inside this crate.
+extern crate self as siirs;
+
 pub mod achievements;
+pub mod defs;
+pub mod game;
+pub mod locale;
 pub mod scs;
 pub mod sii;
 pub mod sqlite;
-pub mod crypt;
\ No newline at end of file
+pub mod crypt;
+pub mod profiles;
+
+#[cfg(test)]
+pub(crate) mod test_util;
@@ -0,0 +1,344 @@
+use anyhow::{anyhow, bail, Result};
+use std::{collections::HashMap, fmt::Display, io::Read, path::{Path, PathBuf}};
+
+use crate::{game::Game, scs::{self, hash_path, Archive}, sii::{format, text::{Lexer, Parser}, value::Struct}, take_value_as};
+
+pub struct LocaleDB {
+    entries: HashMap<String, String>,
+    skipped: scs::Skipped,
+}
+
+impl LocaleDB {
+    pub const DEFAULT_LANGUAGE: &'static str = "en_us";
+
+    pub fn new_empty() -> Self {
+        Self { entries: HashMap::new(), skipped: Vec::new() }
+    }
+
+    pub fn new_from_reader<R: Read>(reader: &mut R) -> Result<Self> {
+        let lex = Lexer::new(reader.bytes().peekable());
+        let mut parser = Parser::new(lex)?;
+        let db_struct = parser
+            .next()
+            .ok_or_else(|| anyhow!("missing localization_db struct"))??;
+
+        let mut db = Self::new_empty();
+        db.merge_struct(db_struct)?;
+        Ok(db)
+    }
+
+    /// Loads `lang` from the game's `locale.scs` and DLC archives, then from
+    /// `mods` in order, each file overriding keys from the ones before it.
+    /// Keys missing from `lang` fall back to en_us.  Fails if `game_path` is
+    /// recognizably an install of another game than `game`.
+    ///
+    /// Only `locale.scs` has to be readable; DLC and mod archives that cannot
+    /// be opened or parsed (zipped mods, newer archive versions, truncated
+    /// downloads) are skipped and listed in `skipped`.
+    pub fn load(game_path: &Path, game: Game, lang: &str, mods: &[PathBuf]) -> Result<Self> {
+        if let Some(installed) = Game::detect_install(game_path).filter(|g| *g != game) {
+            bail!("{} is a {} install, not {}", game_path.display(), installed.name(), game.name());
+        }
+        let base_path = game_path.join("locale.scs");
+        let optional = scs::dlc_archives(game_path)?.into_iter().chain(mods.iter().cloned());
+        let (archives, skipped) = scs::open_archives(&base_path, optional)?;
+
+        let mut db = Self { skipped, ..Self::new_empty() };
+        let mut languages = vec![Self::DEFAULT_LANGUAGE];
+        if lang != Self::DEFAULT_LANGUAGE {
+            languages.push(lang);
+        }
+        for lang in languages {
+            let mut found = false;
+            for (path, archive) in &archives {
+                match Self::read_archive(archive, lang) {
+                    Ok(Some(entries)) => {
+                        db.entries.extend(entries);
+                        found = true;
+                    }
+                    Ok(None) => {}
+                    Err(e) if *path == base_path => return Err(e),
+                    Err(e) => db.skipped.push((path.clone(), e.context(format!("reading '{}'", lang)))),
+                }
+            }
+            if !found {
+                bail!("no locale files for '{}' in {}", lang, game_path.display());
+            }
+        }
+
+        Ok(db)
+    }
+
+    /// Reads `locale/<lang>/local.sii` and then any other `local.*.sii`
+    /// files in `archive`, or `None` if there are none.  Everything is read
+    /// before any of it is merged, so a bad file leaves nothing from the
+    /// archive half applied.
+    fn read_archive(archive: &Archive, lang: &str) -> Result<Option<Vec<(String, String)>>> {
+        let dir = format!("locale/{}", lang);
+        let mut files = match archive.read_dir(&dir) {
+            Ok(listing) => listing
+                .into_iter()
+                .filter(|e| !e.is_dir && e.name.starts_with("local") && e.name.ends_with(".sii"))
+                .map(|e| e.name)
+                .collect(),
+            // Archives are not required to have directory entries.
+            Err(_) => vec!["local.sii".to_owned()],
+        };
+        files.sort_by_key(|f| (f != "local.sii", f.clone()));
+
+        let mut entries = None;
+        for file in files {
+            let path = format!("{}/{}", dir, file);
+            if archive.describe_entry(hash_path(&path)).is_none() {
+                continue;
+            }
+
+            let mut data = Vec::new();
+            archive.open_path(&path)?.read_to_end(&mut data)?;
+            let entries = entries.get_or_insert_with(Vec::new);
+            for s in format::read_structs(&data)? {
+                entries.extend(Self::struct_entries(s)?);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// The DLC and mod archives `load` left out, with the reason for each.
+    pub fn skipped(&self) -> &[(PathBuf, anyhow::Error)] {
+        &self.skipped
+    }
+
+    fn merge_struct(&mut self, db_struct: Struct) -> Result<()> {
+        self.entries.extend(Self::struct_entries(db_struct)?);
+        Ok(())
+    }
+
+    fn struct_entries(mut db_struct: Struct) -> Result<Vec<(String, String)>> {
+        let keys = take_value_as!(db_struct, "key", StringArray)?;
+        let values = take_value_as!(db_struct, "val", StringArray)?;
+        Ok(keys.into_iter().zip(values).collect())
+    }
+
+    pub fn try_localize(&self, key: &str) -> Option<&String> {
+        self.entries.get(key)
+    }
+
+    /// Replaces every `@@key@@` in `s` with the key's expanded value.  Unknown
+    /// keys are left as they are; a key that refers back to itself is an
+    /// error.
+    pub fn expand(&self, s: &str) -> Result<String> {
+        self.expand_inner(s, &mut Vec::new(), false)
+    }
+
+    /// Like `expand`, but `None` unless every `@@key@@` in `s` is known.
+    pub fn resolve(&self, s: &str) -> Option<String> {
+        self.expand_inner(s, &mut Vec::new(), true).ok()
+    }
+
+    fn expand_inner<'a>(
+        &'a self,
+        s: &str,
+        stack: &mut Vec<&'a str>,
+        strict: bool,
+    ) -> Result<String> {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find("@@") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("@@") else {
+                rest = &rest[start..];
+                break;
+            };
+
+            let key = &after[..end];
+            match self.entries.get_key_value(key) {
+                Some((k, v)) => {
+                    if stack.contains(&k.as_str()) {
+                        bail!("locale key '{}' refers to itself via {:?}", k, stack);
+                    }
+                    stack.push(k);
+                    out.push_str(&self.expand_inner(v, stack, strict)?);
+                    stack.pop();
+                }
+                None if strict => bail!("unknown locale key '{}'", key),
+                None => out.push_str(&rest[start..start + end + 4]),
+            }
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+
+        Ok(out)
+    }
+
+    /// Expands `s` and fills its printf style placeholders (`%s`, `%d`, `%u`,
+    /// `%.1f`, ...) from `args` in order.  `%%` is a literal percent sign.
+    pub fn format(&self, s: &str, args: &[&dyn Display]) -> Result<String> {
+        let expanded = self.expand(s)?;
+        let mut out = String::with_capacity(expanded.len());
+        let mut args = args.iter();
+        let mut chars = expanded.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            if chars.peek() == Some(&'%') {
+                chars.next();
+                out.push('%');
+                continue;
+            }
+
+            // Flags and width are ignored; only the precision is honored.
+            let mut spec = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | ' ' | '#' | 'l') {
+                    spec.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            match chars.next() {
+                Some('s' | 'd' | 'i' | 'u' | 'f' | 'g') => {
+                    let arg = args
+                        .next()
+                        .ok_or_else(|| anyhow!("not enough arguments for '{}'", s))?;
+                    match spec.split_once('.') {
+                        Some((_, precision)) => {
+                            out.push_str(&format!("{:.*}", precision.parse().unwrap_or(0), arg))
+                        }
+                        None => out.push_str(&arg.to_string()),
+                    }
+                }
+                // Not a placeholder after all.
+                other => {
+                    out.push('%');
+                    out.push_str(&spec);
+                    out.extend(other);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Looks up and formats `key`, if it exists.
+    pub fn localize_with(&self, key: &str, args: &[&dyn Display]) -> Option<Result<String>> {
+        self.try_localize(key).map(|v| self.format(v, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+
+    use crate::{
+        game::Game,
+        test_util::{temp_dir, write_archive},
+    };
+
+    use super::LocaleDB;
+
+    fn local_sii(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut s = "SiiNunit\n{\nlocalization_db : .localization\n{\n".to_owned();
+        for (k, v) in entries {
+            s += &format!(" key[]: \"{}\"\n val[]: \"{}\"\n", k, v);
+        }
+        s += "}\n}\n";
+        s.into_bytes()
+    }
+
+    #[test]
+    fn merges_languages_dlcs_and_mods() -> Result<()> {
+        let dir = temp_dir("locale")?;
+        let write = |name: &str, files: &[(&str, Vec<u8>)]| write_archive(&dir.join(name), files);
+
+        write(
+            "locale.scs",
+            &[
+                ("locale/en_us/local.sii", local_sii(&[("berlin", "Berlin"), ("apples", "Apples")])),
+                ("locale/de_de/local.sii", local_sii(&[("apples", "Äpfel"), ("milk", "Milch")])),
+                ("locale/de_de/local.override.sii", local_sii(&[("milk", "Frischmilch")])),
+            ],
+        )?;
+        write(
+            "dlc_north.scs",
+            &[("locale/de_de/local.dlc_north.sii", local_sii(&[("oslo", "Oslo")]))],
+        )?;
+        write("mod.scs", &[("locale/de_de/local.mod.sii", local_sii(&[("apples", "Mod-Äpfel")]))])?;
+
+        let de = LocaleDB::load(&dir, Game::Ets2, "de_de", &[])?;
+        let modded = LocaleDB::load(&dir, Game::Ets2, "de_de", &[dir.join("mod.scs")]);
+        let missing = LocaleDB::load(&dir, Game::Ets2, "cs_cz", &[]);
+        fs::create_dir_all(dir.join("bin/win_x64"))?;
+        fs::write(dir.join("bin/win_x64/amtrucks.exe"), b"")?;
+        let wrong_game = LocaleDB::load(&dir, Game::Ets2, "de_de", &[]);
+        fs::remove_dir_all(&dir)?;
+
+        let get = |db: &LocaleDB, k: &str| db.try_localize(k).cloned();
+        assert_eq!(get(&de, "berlin").as_deref(), Some("Berlin"));
+        assert_eq!(get(&de, "apples").as_deref(), Some("Äpfel"));
+        assert_eq!(get(&de, "milk").as_deref(), Some("Frischmilch"));
+        assert_eq!(get(&de, "oslo").as_deref(), Some("Oslo"));
+        assert_eq!(get(&modded?, "apples").as_deref(), Some("Mod-Äpfel"));
+        assert!(missing.is_err());
+        assert!(wrong_game.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn skips_unreadable_dlc_and_mod_archives() -> Result<()> {
+        let dir = temp_dir("locale_bad")?;
+        let write = |name: &str, files: &[(&str, Vec<u8>)]| write_archive(&dir.join(name), files);
+
+        write("locale.scs", &[("locale/en_us/local.sii", local_sii(&[("berlin", "Berlin")]))])?;
+        // Not an archive at all, e.g. a zipped DLC or a truncated download.
+        fs::write(dir.join("dlc_a_broken.scs"), b"PK\x03\x04 not a hashfs archive")?;
+        // An archive whose locale file does not parse.
+        write("dlc_b_garbled.scs", &[("locale/en_us/local.sii", b"SiiNunit { oops".to_vec())])?;
+        write("dlc_c_north.scs", &[("locale/en_us/local.dlc_north.sii", local_sii(&[("oslo", "Oslo")]))])?;
+        fs::write(dir.join("mod.scs"), b"")?;
+
+        let db = LocaleDB::load(&dir, Game::Ets2, "en_us", &[dir.join("mod.scs")]);
+        fs::write(dir.join("locale.scs"), b"truncated")?;
+        let broken_base = LocaleDB::load(&dir, Game::Ets2, "en_us", &[]);
+        fs::remove_dir_all(&dir)?;
+
+        let db = db?;
+        assert_eq!(db.try_localize("berlin").map(String::as_str), Some("Berlin"));
+        assert_eq!(db.try_localize("oslo").map(String::as_str), Some("Oslo"));
+        let skipped: Vec<_> = db.skipped().iter().map(|(p, _)| p.clone()).collect();
+        assert_eq!(
+            skipped,
+            [dir.join("dlc_a_broken.scs"), dir.join("mod.scs"), dir.join("dlc_b_garbled.scs")]
+        );
+        assert!(broken_base.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn expands_references_and_placeholders() -> Result<()> {
+        let db = LocaleDB::new_from_reader(&mut &local_sii(&[
+            ("cn_apples", "Apples"),
+            ("fresh", "Fresh @@cn_apples@@"),
+            ("deliver", "Deliver %u loads of @@fresh@@ (%.1f%% done, %s)"),
+            ("loop_a", "@@loop_b@@"),
+            ("loop_b", "x @@loop_a@@"),
+        ])[..])?;
+
+        assert_eq!(db.expand("@@fresh@@ and @@unknown@@ @@")?, "Fresh Apples and @@unknown@@ @@");
+        assert_eq!(
+            db.localize_with("deliver", &[&3, &42.25, &"nearly"]).unwrap()?,
+            "Deliver 3 loads of Fresh Apples (42.2% done, nearly)"
+        );
+        assert!(db.format("%u and %u", &[&1]).is_err());
+        assert!(db.expand("@@loop_a@@").is_err());
+        assert_eq!(db.resolve("@@fresh@@ @@").as_deref(), Some("Fresh Apples @@"));
+        assert_eq!(db.resolve("@@fresh@@ and @@unknown@@"), None);
+        Ok(())
+    }
+}
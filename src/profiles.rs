@@ -0,0 +1,272 @@
+//! Finds profiles and save slots under an ETS2/ATS documents folder, e.g.
+//! `Documents/Euro Truck Simulator 2`.
+//!
+//! Profiles live in `profiles/<hex encoded name>/` (or `steam_profiles/` for
+//! Steam Cloud profiles), with a `profile.sii` and a `save/<slot>/` directory
+//! per save, each holding an `info.sii` and the `game.sii` itself.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::sii::game::{SaveContainer, UserProfile};
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub dir: PathBuf,
+    pub name: String,
+    pub is_steam: bool,
+    /// Unix time the profile was last saved.
+    pub save_time: u64,
+    /// `profile.sii`, if it could be read.
+    pub info: Option<UserProfile>,
+    /// Newest first.
+    pub saves: Vec<SaveSlot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SaveSlot {
+    pub dir: PathBuf,
+    /// Directory name, e.g. `autosave`, `quicksave` or `3`.
+    pub slot: String,
+    /// Name given to the save in game; empty for most automatic saves.
+    pub name: String,
+    /// Unix time the save was written.
+    pub file_time: u64,
+    /// `info.sii`, if it could be read.
+    pub info: Option<SaveContainer>,
+}
+
+impl SaveSlot {
+    pub fn game_sii(&self) -> PathBuf {
+        self.dir.join("game.sii")
+    }
+
+    pub fn is_autosave(&self) -> bool {
+        self.slot.starts_with("autosave")
+    }
+}
+
+impl Profile {
+    pub fn latest_save(&self) -> Option<&SaveSlot> {
+        self.saves.first()
+    }
+
+    pub fn latest_autosave(&self) -> Option<&SaveSlot> {
+        self.saves.iter().find(|s| s.is_autosave())
+    }
+
+    /// The `.scs` files in `<documents>/mod` for the profile's active mods,
+    /// lowest priority first.  Packed `.zip` mods are not supported.
+    pub fn mod_archives(&self, documents: &Path) -> Vec<PathBuf> {
+        let Some(info) = &self.info else {
+            return Vec::new();
+        };
+
+        // The first active mod is the top of the in-game list, and wins.
+        info.active_mods
+            .iter()
+            .rev()
+            .map(|m| documents.join("mod").join(format!("{}.scs", m.id)))
+            .filter(|p| p.is_file())
+            .collect()
+    }
+}
+
+/// Profile directories are named after the hex encoded UTF-8 profile name.
+pub fn decode_profile_dir_name(name: &str) -> Option<String> {
+    if !name.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = (0..name.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(name.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Every profile under `documents`, most recently saved first.  Profiles and
+/// slots that can't be read are skipped.
+pub fn find_profiles(documents: &Path) -> Result<Vec<Profile>> {
+    let mut profiles = Vec::new();
+    let mut found_dir = false;
+
+    for (subdir, is_steam) in [("profiles", false), ("steam_profiles", true)] {
+        let dir = documents.join(subdir);
+        if !dir.is_dir() {
+            continue;
+        }
+
+        found_dir = true;
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                if let Some(p) = read_profile(&entry.path(), is_steam) {
+                    profiles.push(p);
+                }
+            }
+        }
+    }
+
+    if !found_dir {
+        bail!("no profiles or steam_profiles directory in {}", documents.display());
+    }
+
+    profiles.sort_by_key(|p| std::cmp::Reverse(p.save_time));
+    Ok(profiles)
+}
+
+/// The newest autosave of the most recently saved profile.
+pub fn latest_autosave(documents: &Path) -> Result<SaveSlot> {
+    find_profiles(documents)?
+        .iter()
+        .find_map(|p| p.latest_autosave().cloned())
+        .ok_or_else(|| anyhow!("no autosaves found in {}", documents.display()))
+}
+
+fn modified_time(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+fn read_profile(dir: &Path, is_steam: bool) -> Option<Profile> {
+    let profile_sii = dir.join("profile.sii");
+    if !profile_sii.is_file() {
+        return None;
+    }
+
+    let info = UserProfile::load(&profile_sii).ok();
+    let dir_name = dir.file_name()?.to_string_lossy().into_owned();
+    let name = info
+        .as_ref()
+        .map(|p| p.profile_name.clone())
+        .or_else(|| decode_profile_dir_name(&dir_name))
+        .unwrap_or(dir_name);
+    let save_time = info
+        .as_ref()
+        .map_or_else(|| modified_time(&profile_sii), |p| p.save_time);
+
+    let mut saves = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir.join("save")) {
+        for entry in entries.flatten() {
+            if let Some(slot) = read_save_slot(&entry.path()) {
+                saves.push(slot);
+            }
+        }
+    }
+    saves.sort_by(|a, b| b.file_time.cmp(&a.file_time).then_with(|| a.slot.cmp(&b.slot)));
+
+    Some(Profile {
+        dir: dir.to_owned(),
+        name,
+        is_steam,
+        save_time,
+        info,
+        saves,
+    })
+}
+
+fn read_save_slot(dir: &Path) -> Option<SaveSlot> {
+    if !dir.join("game.sii").is_file() {
+        return None;
+    }
+
+    let info = SaveContainer::load(&dir.join("info.sii")).ok();
+
+    Some(SaveSlot {
+        dir: dir.to_owned(),
+        slot: dir.file_name()?.to_string_lossy().into_owned(),
+        name: info.as_ref().map(|c| c.name.clone()).unwrap_or_default(),
+        file_time: info
+            .as_ref()
+            .map_or_else(|| modified_time(&dir.join("game.sii")), |c| c.file_time),
+        info,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use anyhow::Result;
+
+    use crate::test_util::temp_path;
+
+    use super::{decode_profile_dir_name, find_profiles, latest_autosave};
+
+    fn write_text_sii(path: &Path, struct_name: &str, fields: &str) -> Result<()> {
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(
+            path,
+            format!("SiiNunit\n{{\n{struct_name} : x.y {{\n{fields}\n}}\n}}\n"),
+        )?;
+        Ok(())
+    }
+
+    fn user_profile(name: &str, save_time: u64) -> String {
+        format!(
+            "profile_name: \"{name}\"\ncompany_name: c\nface: 0\nbrand: b\nlogo: l\nmale: true\n\
+             map_path: m\ncached_experience: 0\ncached_distance: 0\nversion: 1\n\
+             creation_time: 0\nsave_time: {save_time}"
+        )
+    }
+
+    fn write_slot(profile: &Path, slot: &str, file_time: u64) -> Result<()> {
+        let dir = profile.join("save").join(slot);
+        write_text_sii(
+            &dir.join("info.sii"),
+            "save_container",
+            &format!("name: \"{slot}\"\ntime: 10\nfile_time: {file_time}\nversion: 1"),
+        )?;
+        fs::write(dir.join("game.sii"), b"")?;
+        Ok(())
+    }
+
+    #[test]
+    fn finds_profiles_and_sorts_saves() -> Result<()> {
+        let docs = temp_path("profiles");
+        let alice = docs.join("profiles").join("416C696365");
+        let bob = docs.join("steam_profiles").join("426F62");
+        write_text_sii(&alice.join("profile.sii"), "user_profile", &user_profile("Alice", 200))?;
+        write_text_sii(&bob.join("profile.sii"), "user_profile", &user_profile("Bob", 100))?;
+        write_slot(&alice, "1", 150)?;
+        write_slot(&alice, "autosave", 190)?;
+        write_slot(&alice, "quicksave", 199)?;
+        write_slot(&bob, "autosave", 99)?;
+        fs::create_dir_all(alice.join("save").join("empty"))?;
+
+        let profiles = find_profiles(&docs)?;
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "Alice");
+        assert!(!profiles[0].is_steam);
+        assert_eq!(profiles[1].name, "Bob");
+        assert!(profiles[1].is_steam);
+
+        let slots: Vec<_> = profiles[0].saves.iter().map(|s| s.slot.as_str()).collect();
+        assert_eq!(slots, vec!["quicksave", "autosave", "1"]);
+        assert_eq!(profiles[0].latest_save().unwrap().file_time, 199);
+        assert_eq!(profiles[0].info.as_ref().unwrap().save_time, 200);
+        assert_eq!(profiles[0].saves[0].info.as_ref().unwrap().name, "quicksave");
+        assert_eq!(latest_autosave(&docs)?.game_sii(), alice.join("save/autosave/game.sii"));
+
+        fs::remove_dir_all(&docs)?;
+        assert!(find_profiles(&docs).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_hex_names() {
+        assert_eq!(decode_profile_dir_name("4A6F686E").as_deref(), Some("John"));
+        assert_eq!(decode_profile_dir_name("C3A9").as_deref(), Some("é"));
+        assert_eq!(decode_profile_dir_name("4A6").as_deref(), None);
+        assert_eq!(decode_profile_dir_name("zz").as_deref(), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! CityHash64, as used by HashFS archives to hash entry paths.
+//!
+//! Note that this is the original (1.0.x) version of the algorithm -- later
+//! releases of CityHash changed the output for short inputs, and the hashes
+//! stored in .scs files do not match those.
+
+const K0: u64 = 0xc3a5c85c97cb3127;
+const K1: u64 = 0xb492b66fbe98f273;
+const K2: u64 = 0x9ae16a3b2f90404f;
+const K3: u64 = 0xc949d7c7509e6557;
+
+fn fetch64(s: &[u8]) -> u64 {
+    u64::from_le_bytes(s[..8].try_into().expect("slice has 8 bytes"))
+}
+
+fn fetch32(s: &[u8]) -> u64 {
+    u32::from_le_bytes(s[..4].try_into().expect("slice has 4 bytes")) as u64
+}
+
+fn shift_mix(val: u64) -> u64 {
+    val ^ (val >> 47)
+}
+
+fn hash_len16(u: u64, v: u64) -> u64 {
+    const MUL: u64 = 0x9ddfea08eb382d69;
+    let mut a = (u ^ v).wrapping_mul(MUL);
+    a ^= a >> 47;
+    let mut b = (v ^ a).wrapping_mul(MUL);
+    b ^= b >> 47;
+    b.wrapping_mul(MUL)
+}
+
+fn hash_len0to16(s: &[u8]) -> u64 {
+    let len = s.len();
+    if len > 8 {
+        let a = fetch64(s);
+        let b = fetch64(&s[len - 8..]);
+        return hash_len16(a, b.wrapping_add(len as u64).rotate_right(len as u32)) ^ b;
+    }
+    if len >= 4 {
+        let a = fetch32(s);
+        return hash_len16((len as u64).wrapping_add(a << 3), fetch32(&s[len - 4..]));
+    }
+    if len > 0 {
+        let a = s[0] as u32;
+        let b = s[len >> 1] as u32;
+        let c = s[len - 1] as u32;
+        let y = a.wrapping_add(b << 8) as u64;
+        let z = (len as u32).wrapping_add(c << 2) as u64;
+        return shift_mix(y.wrapping_mul(K2) ^ z.wrapping_mul(K3)).wrapping_mul(K2);
+    }
+
+    K2
+}
+
+fn hash_len17to32(s: &[u8]) -> u64 {
+    let len = s.len();
+    let a = fetch64(s).wrapping_mul(K1);
+    let b = fetch64(&s[8..]);
+    let c = fetch64(&s[len - 8..]).wrapping_mul(K2);
+    let d = fetch64(&s[len - 16..]).wrapping_mul(K0);
+    hash_len16(
+        a.wrapping_sub(b)
+            .rotate_right(43)
+            .wrapping_add(c.rotate_right(30))
+            .wrapping_add(d),
+        a.wrapping_add((b ^ K3).rotate_right(20))
+            .wrapping_sub(c)
+            .wrapping_add(len as u64),
+    )
+}
+
+fn weak_hash_len32_with_seeds(s: &[u8], mut a: u64, mut b: u64) -> (u64, u64) {
+    let w = fetch64(s);
+    let x = fetch64(&s[8..]);
+    let y = fetch64(&s[16..]);
+    let z = fetch64(&s[24..]);
+
+    a = a.wrapping_add(w);
+    b = b.wrapping_add(a).wrapping_add(z).rotate_right(21);
+    let c = a;
+    a = a.wrapping_add(x).wrapping_add(y);
+    b = b.wrapping_add(a.rotate_right(44));
+    (a.wrapping_add(z), b.wrapping_add(c))
+}
+
+fn hash_len33to64(s: &[u8]) -> u64 {
+    let len = s.len();
+    let mut z = fetch64(&s[24..]);
+    let mut a = fetch64(s).wrapping_add(
+        (len as u64)
+            .wrapping_add(fetch64(&s[len - 16..]))
+            .wrapping_mul(K0),
+    );
+    let mut b = a.wrapping_add(z).rotate_right(52);
+    let mut c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(&s[8..]));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(&s[16..]));
+    let vf = a.wrapping_add(z);
+    let vs = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+
+    a = fetch64(&s[16..]).wrapping_add(fetch64(&s[len - 32..]));
+    z = fetch64(&s[len - 8..]);
+    b = a.wrapping_add(z).rotate_right(52);
+    c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(&s[len - 24..]));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(&s[len - 16..]));
+    let wf = a.wrapping_add(z);
+    let ws = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+
+    let r = shift_mix(
+        vf.wrapping_add(ws)
+            .wrapping_mul(K2)
+            .wrapping_add(wf.wrapping_add(vs).wrapping_mul(K0)),
+    );
+    shift_mix(r.wrapping_mul(K0).wrapping_add(vs)).wrapping_mul(K2)
+}
+
+pub fn city_hash64(s: &[u8]) -> u64 {
+    let len = s.len();
+    if len <= 16 {
+        return hash_len0to16(s);
+    } else if len <= 32 {
+        return hash_len17to32(s);
+    } else if len <= 64 {
+        return hash_len33to64(s);
+    }
+
+    // For strings over 64 bytes, hash the end first, and then loop over
+    // 64-byte chunks keeping 56 bytes of state.
+    let mut x = fetch64(s);
+    let mut y = fetch64(&s[len - 16..]) ^ K1;
+    let mut z = fetch64(&s[len - 56..]) ^ K0;
+    let mut v = weak_hash_len32_with_seeds(&s[len - 64..], len as u64, y);
+    let mut w = weak_hash_len32_with_seeds(&s[len - 32..], (len as u64).wrapping_mul(K1), K0);
+    z = z.wrapping_add(shift_mix(v.1).wrapping_mul(K1));
+    x = z.wrapping_add(x).rotate_right(39).wrapping_mul(K1);
+    y = y.rotate_right(33).wrapping_mul(K1);
+
+    for chunk in s[..(len - 1) & !63].chunks_exact(64) {
+        x = x
+            .wrapping_add(y)
+            .wrapping_add(v.0)
+            .wrapping_add(fetch64(&chunk[16..]))
+            .rotate_right(37)
+            .wrapping_mul(K1);
+        y = y
+            .wrapping_add(v.1)
+            .wrapping_add(fetch64(&chunk[48..]))
+            .rotate_right(42)
+            .wrapping_mul(K1);
+        x ^= w.1;
+        y ^= v.0;
+        z = (z ^ w.0).rotate_right(33);
+        v = weak_hash_len32_with_seeds(chunk, v.1.wrapping_mul(K1), x.wrapping_add(w.0));
+        w = weak_hash_len32_with_seeds(&chunk[32..], z.wrapping_add(w.1), y);
+        std::mem::swap(&mut z, &mut x);
+    }
+
+    hash_len16(
+        hash_len16(v.0, w.0)
+            .wrapping_add(shift_mix(y).wrapping_mul(K1))
+            .wrapping_add(z),
+        hash_len16(v.1, w.1).wrapping_add(x),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::city_hash64;
+
+    #[test]
+    fn known_scs_paths() {
+        assert_eq!(city_hash64(b""), 0x9AE16A3B2F90404F);
+        assert_eq!(city_hash64(b"def/achievements.sii"), 0x5C075DC23D8D177);
+        assert_eq!(city_hash64(b"locale/en_us/local.sii"), 0x748A55BF49E4F39E);
+    }
+}
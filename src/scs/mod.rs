@@ -1,7 +1,59 @@
 use anyhow::{Result, bail};
 use byteorder::{ReadBytesExt, BigEndian, LittleEndian};
 use flate2::read::ZlibDecoder;
-use std::{fs::File, io::{Seek, Read}, collections::HashMap};
+use std::{fs::{self, File}, io::{Seek, SeekFrom, Read}, collections::{HashMap, VecDeque}, fmt::Display, path::{Path, PathBuf}};
+
+pub use self::cityhash::city_hash64;
+
+mod cityhash;
+pub mod writer;
+
+/// Hashes a path the way HashFS stores it in the entry table: relative to the
+/// archive root, without a leading slash.  The root directory is the empty
+/// path.
+pub fn hash_path(path: &str) -> u64 {
+    city_hash64(path.trim_matches('/').as_bytes())
+}
+
+/// The `dlc_*.scs` archives in a game folder, in load order.
+pub fn dlc_archives(game_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut dlcs = fs::read_dir(game_path)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("dlc_") && n.ends_with(".scs"))
+        })
+        .collect::<Vec<_>>();
+    dlcs.sort();
+    Ok(dlcs)
+}
+
+/// Files that were left out of a load, with the reason for each.
+pub type Skipped = Vec<(PathBuf, anyhow::Error)>;
+
+/// Opens `required` and then each of `optional` in order.  Optional
+/// archives that cannot be opened (zipped mods, newer archive versions,
+/// truncated downloads) are returned as skipped rather than failing the
+/// rest.
+pub fn open_archives<I>(required: &Path, optional: I) -> Result<(Vec<(PathBuf, Archive)>, Skipped)>
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let mut archives = vec![(required.to_owned(), Archive::load_from_path(path_str(required)?)?)];
+    let mut skipped = Vec::new();
+    for path in optional {
+        match path_str(&path).and_then(Archive::load_from_path) {
+            Ok(archive) => archives.push((path, archive)),
+            Err(e) => skipped.push((path, e)),
+        }
+    }
+    Ok((archives, skipped))
+}
+
+fn path_str(path: &Path) -> Result<&str> {
+    path.to_str().ok_or_else(|| anyhow::anyhow!("illegal filename {}", path.display()))
+}
 
 pub struct Archive {
     file: File,
@@ -30,6 +82,23 @@ impl TryFrom<u32> for EntryType {
     }
 }
 
+impl EntryType {
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Self::UncompressedDirEntries | Self::CompressedDirEntries)
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self, Self::CompressedFile | Self::CompressedDirEntries)
+    }
+}
+
+/// One line of a directory entry's listing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DirListing {
+    pub name: String,
+    pub is_dir: bool,
+}
+
 #[derive(Debug)]
 pub struct Entry {
     pub hash: u64,
@@ -97,78 +166,540 @@ impl Archive {
         self.entries.get(&hash)
     }
 
-    pub fn open_entry(&mut self, hash: u64) -> Result<EntryReader> {
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.values()
+    }
+
+    pub fn open_path(&self, path: &str) -> Result<EntryReader<'_>> {
+        self.open_entry(hash_path(path))
+    }
+
+    /// Lists the children of a directory.  Directory entries are just a list
+    /// of names, one per line, with subdirectories prefixed by `*`.
+    pub fn read_dir(&self, path: &str) -> Result<Vec<DirListing>> {
+        let hash = hash_path(path);
+        match self.describe_entry(hash) {
+            Some(e) if e.entry_type.is_dir() => {}
+            Some(_) => bail!("'{path}' is not a directory"),
+            None => bail!("no such directory '{path}'"),
+        }
+
+        let mut contents = String::new();
+        self.open_entry(hash)?.read_to_string(&mut contents)?;
+
+        Ok(contents
+            .lines()
+            .filter(|l| !l.is_empty())
+            .map(|l| match l.strip_prefix('*') {
+                Some(name) => DirListing { name: name.to_owned(), is_dir: true },
+                None => DirListing { name: l.to_owned(), is_dir: false },
+            })
+            .collect())
+    }
+
+    /// Walks the directory tree from the root and returns the path of every
+    /// entry that could be reached.  Archives are not required to contain
+    /// directory entries for everything (mods commonly leave them out), so
+    /// entries missing from the result are simply unnamed.
+    pub fn resolve_paths(&self) -> Result<HashMap<u64, String>> {
+        let mut paths = HashMap::with_capacity(self.entries.len());
+        let mut pending = VecDeque::from([String::new()]);
+
+        while let Some(dir) = pending.pop_front() {
+            let hash = hash_path(&dir);
+            if !self.describe_entry(hash).is_some_and(|e| e.entry_type.is_dir()) {
+                continue;
+            }
+            paths.insert(hash, dir.clone());
+
+            for child in self.read_dir(&dir)? {
+                let path = if dir.is_empty() {
+                    child.name
+                } else {
+                    format!("{dir}/{}", child.name)
+                };
+
+                if child.is_dir {
+                    pending.push_back(path);
+                } else {
+                    let hash = hash_path(&path);
+                    if self.entries.contains_key(&hash) {
+                        paths.insert(hash, path);
+                    }
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Opens an entry for reading.  Entry readers use positional reads and
+    /// don't share a file cursor, so any number of them can be open at once,
+    /// from any number of threads.
+    pub fn open_entry(&self, hash: u64) -> Result<EntryReader<'_>> {
         if let Some(entry) = self.describe_entry(hash) {
             let offset = entry.offset;
             let entry_type = entry.entry_type;
             let size = entry.size;
-            self.file.seek(std::io::SeekFrom::Start(offset as u64))?;
-
-            match entry_type {
-                EntryType::UncompressedFile => {
-                    Ok(EntryReader::UncompressedReader {
-                        reader: UncompressedReader { 
-                            file: &mut self.file,
-                            length: size as usize,
-                            i: 0
-                        }
-                    })
-                }
-                EntryType::CompressedFile => {
-                    Ok(EntryReader::CompressedReader {
-                        decoder: ZlibDecoder::new(&mut self.file)
-                    })
-                }
-                t => bail!("unsupported entry type {t:?}")
-            }
+            let zsize = entry.zsize;
+            let crc32 = entry.crc32;
+            let source = if entry_type.is_compressed() {
+                EntrySource::Compressed(ZlibDecoder::new(FileRange::new(&self.file, offset, zsize)))
+            } else {
+                EntrySource::Uncompressed(FileRange::new(&self.file, offset, size))
+            };
+
+            Ok(EntryReader {
+                source,
+                hash,
+                size: size as u64,
+                crc32,
+                pos: 0,
+                hasher: None,
+            })
         } else {
             bail!("no such entry with hash {hash:X}")
         }
     }
 }
 
-pub struct UncompressedReader<'a> {
-    file: &'a mut File,
-    length: usize,
-    i: usize,
+/// Returned (wrapped in an `std::io::Error` of kind `InvalidData`) when the
+/// data read for an entry does not match what the entry table says it should
+/// be.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CorruptEntryError {
+    SizeMismatch { hash: u64, expected: u64, actual: u64 },
+    Crc32Mismatch { hash: u64, expected: u32, actual: u32 },
+}
+
+impl Display for CorruptEntryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SizeMismatch { hash, expected, actual } => write!(
+                f,
+                "entry {hash:X} is corrupt: expected {expected} bytes but read {actual}"
+            ),
+            Self::Crc32Mismatch { hash, expected, actual } => write!(
+                f,
+                "entry {hash:X} is corrupt: expected crc32 {expected:08X} but computed {actual:08X}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CorruptEntryError {}
+
+impl From<CorruptEntryError> for std::io::Error {
+    fn from(value: CorruptEntryError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, value)
+    }
+}
+
+/// Reads `[offset, offset + len)` of a file with positional reads, leaving the
+/// file's own cursor alone.
+struct FileRange<'a> {
+    file: &'a File,
+    start: u64,
+    pos: u64,
+    end: u64,
 }
 
-impl UncompressedReader<'_> {
-    fn remaining(&self) -> usize {
-        self.length - self.i
+impl<'a> FileRange<'a> {
+    fn new(file: &'a File, offset: u32, len: u32) -> Self {
+        Self {
+            file,
+            start: offset as u64,
+            pos: offset as u64,
+            end: offset as u64 + len as u64,
+        }
+    }
+
+    fn rewound(&self) -> Self {
+        Self {
+            pos: self.start,
+            ..*self
+        }
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::os::unix::fs::FileExt::read_at(self.file, buf, self.pos)
+    }
+
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::os::windows::fs::FileExt::seek_read(self.file, buf, self.pos)
     }
 }
 
-impl<'a> Read for UncompressedReader<'a> {
+impl Read for FileRange<'_> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let n = std::cmp::min(buf.len(), self.remaining());
-        let res = self.file.read(&mut buf[..n])?;
-        self.i += res;
+        let n = std::cmp::min(buf.len() as u64, self.end - self.pos) as usize;
+        let res = self.read_at(&mut buf[..n])?;
+        self.pos += res as u64;
         Ok(res)
     }
 }
 
-pub enum EntryReader<'a> {
-    UncompressedReader {
-        reader: UncompressedReader<'a>
-    },
-    CompressedReader {
-        // XXX: assumption here is that the deflate stream will EOF on its own,
-        // so the underlying File reader is not limited by zsize.  This works
-        // fine for the entries I care about.
-        decoder: ZlibDecoder<&'a mut File>
+enum EntrySource<'a> {
+    Uncompressed(FileRange<'a>),
+    Compressed(ZlibDecoder<FileRange<'a>>),
+}
+
+/// Reads the contents of a single archive entry.  Reads never go past the
+/// entry's (compressed) data in the archive, and reaching EOF with a different
+/// number of bytes than the entry's declared size is reported as a
+/// `CorruptEntryError::SizeMismatch`.
+///
+/// Seeking is cheap for uncompressed entries.  Compressed entries have to be
+/// inflated up to the target position, starting over from the beginning of
+/// the entry when seeking backwards.
+pub struct EntryReader<'a> {
+    source: EntrySource<'a>,
+    hash: u64,
+    size: u64,
+    crc32: u32,
+    pos: u64,
+    hasher: Option<crc32fast::Hasher>,
+}
+
+impl EntryReader<'_> {
+    /// Uncompressed size of the entry.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.source, EntrySource::Compressed(_))
+    }
+
+    /// Also check the CRC32 of the entry contents once the reader hits EOF.
+    /// Must be called before reading; entries that are not read to the end,
+    /// or that are seeked around in, are not checked.
+    pub fn with_crc32_check(mut self) -> Self {
+        self.hasher = Some(crc32fast::Hasher::new());
+        self
+    }
+
+    fn check_eof(&mut self) -> std::io::Result<()> {
+        if self.pos != self.size {
+            return Err(CorruptEntryError::SizeMismatch {
+                hash: self.hash,
+                expected: self.size,
+                actual: self.pos,
+            }
+            .into());
+        }
+
+        if let Some(hasher) = self.hasher.take() {
+            let actual = hasher.finalize();
+            if actual != self.crc32 {
+                return Err(CorruptEntryError::Crc32Mismatch {
+                    hash: self.hash,
+                    expected: self.crc32,
+                    actual,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
     }
 }
 
-impl<'a> Read for EntryReader<'a> {
+impl Read for EntryReader<'_> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        match self {
-            Self::UncompressedReader { reader } => {
-                reader.read(buf)
-            },
-            Self::CompressedReader { decoder } => {
-                decoder.read(buf)
+        let n = match &mut self.source {
+            EntrySource::Uncompressed(reader) => reader.read(buf)?,
+            EntrySource::Compressed(decoder) => decoder.read(buf)?,
+        };
+
+        if n == 0 && !buf.is_empty() {
+            self.check_eof()?;
+            return Ok(0);
+        }
+
+        self.pos += n as u64;
+        if self.pos > self.size {
+            return Err(CorruptEntryError::SizeMismatch {
+                hash: self.hash,
+                expected: self.size,
+                actual: self.pos,
+            }
+            .into());
+        }
+
+        if let Some(ref mut hasher) = self.hasher {
+            hasher.update(&buf[..n]);
+        }
+
+        Ok(n)
+    }
+}
+
+impl Seek for EntryReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .filter(|t| *t <= self.size)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("seek outside of entry {:X} ({pos:?})", self.hash),
+            )
+        })?;
+
+        if target == self.pos {
+            return Ok(target);
+        }
+        self.hasher = None;
+
+        match &mut self.source {
+            EntrySource::Uncompressed(reader) => {
+                reader.pos = reader.start + target;
+            }
+            EntrySource::Compressed(decoder) => {
+                let mut skip_from = self.pos;
+                if target < self.pos {
+                    *decoder = ZlibDecoder::new(decoder.get_ref().rewound());
+                    skip_from = 0;
+                }
+
+                let skip = target - skip_from;
+                let skipped = std::io::copy(&mut decoder.take(skip), &mut std::io::sink())?;
+                if skipped != skip {
+                    return Err(CorruptEntryError::SizeMismatch {
+                        hash: self.hash,
+                        expected: self.size,
+                        actual: skip_from + skipped,
+                    }
+                    .into());
+                }
+            }
+        }
+
+        self.pos = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::{Read, Seek, SeekFrom, Write}, path::PathBuf};
+
+    use anyhow::Result;
+    use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+    use flate2::{write::ZlibEncoder, Compression};
+
+    use crate::test_util::temp_path;
+
+    use super::{hash_path, Archive, CorruptEntryError};
+
+    // (hash, entry type, crc32, size, stored data)
+    type TestEntry = (u64, u32, u32, u32, Vec<u8>);
+
+    fn write_archive(name: &str, entries: &[TestEntry]) -> Result<PathBuf> {
+        let path = temp_path(&format!("{name}.scs"));
+        let mut f = File::create(&path)?;
+        let header_len = 20u32;
+        let data_len: u32 = entries.iter().map(|e| e.4.len() as u32).sum();
+
+        f.write_u32::<BigEndian>(u32::from_be_bytes(*b"SCS#"))?;
+        f.write_u32::<LittleEndian>(1)?;
+        f.write_u32::<BigEndian>(u32::from_be_bytes(*b"CITY"))?;
+        f.write_u32::<LittleEndian>(entries.len() as u32)?;
+        f.write_u32::<LittleEndian>(header_len + data_len)?;
+        for e in entries {
+            f.write_all(&e.4)?;
+        }
+
+        let mut offset = header_len;
+        for (hash, entry_type, crc32, size, data) in entries {
+            f.write_u64::<LittleEndian>(*hash)?;
+            f.write_u32::<LittleEndian>(offset)?;
+            f.write_u32::<LittleEndian>(0)?;
+            f.write_u32::<LittleEndian>(*entry_type)?;
+            f.write_u32::<LittleEndian>(*crc32)?;
+            f.write_u32::<LittleEndian>(*size)?;
+            f.write_u32::<LittleEndian>(data.len() as u32)?;
+            offset += data.len() as u32;
+        }
+
+        Ok(path)
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(data).unwrap();
+        enc.finish().unwrap()
+    }
+
+    fn corruption(e: &std::io::Error) -> Option<CorruptEntryError> {
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<CorruptEntryError>())
+            .copied()
+    }
+
+    #[test]
+    fn reads_are_bounded_and_verified() -> Result<()> {
+        let plain = b"hello, world".to_vec();
+        let packed = b"compressed entry contents".to_vec();
+        let crc = crc32fast::hash(&packed);
+        let path = write_archive(
+            "bounded",
+            &[
+                (1, 0, crc32fast::hash(&plain), plain.len() as u32, plain.clone()),
+                (2, 2, crc, packed.len() as u32, zlib(&packed)),
+                // Wrong crc, and a size that doesn't match the stream.
+                (3, 2, crc ^ 1, packed.len() as u32, zlib(&packed)),
+                (4, 2, crc, packed.len() as u32 + 1, zlib(&packed)),
+            ],
+        )?;
+        let archive = Archive::load_from_path(path.to_str().unwrap())?;
+
+        let mut out = Vec::new();
+        archive.open_entry(1)?.with_crc32_check().read_to_end(&mut out)?;
+        assert_eq!(out, plain);
+
+        out.clear();
+        archive.open_entry(2)?.with_crc32_check().read_to_end(&mut out)?;
+        assert_eq!(out, packed);
+
+        out.clear();
+        archive.open_entry(3)?.read_to_end(&mut out)?;
+        assert_eq!(out, packed);
+        let err = archive.open_entry(3)?.with_crc32_check().read_to_end(&mut Vec::new());
+        assert!(matches!(
+            corruption(&err.unwrap_err()),
+            Some(CorruptEntryError::Crc32Mismatch { hash: 3, .. })
+        ));
+
+        let err = archive.open_entry(4)?.read_to_end(&mut Vec::new());
+        assert!(matches!(
+            corruption(&err.unwrap_err()),
+            Some(CorruptEntryError::SizeMismatch { hash: 4, expected: 26, actual: 25 })
+        ));
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_paths_from_directory_entries() -> Result<()> {
+        let root = b"*def\nreadme.txt\n".to_vec();
+        let def = b"city.sii\n*missing\n".to_vec();
+        let city = b"SiiNunit {}".to_vec();
+        let path = write_archive(
+            "dirs",
+            &[
+                (hash_path(""), 1, 0, root.len() as u32, root),
+                (hash_path("def"), 3, 0, def.len() as u32, zlib(&def)),
+                (hash_path("def/city.sii"), 0, 0, city.len() as u32, city.clone()),
+                (0x1234, 0, 0, 0, Vec::new()),
+            ],
+        )?;
+        let archive = Archive::load_from_path(path.to_str().unwrap())?;
+
+        let paths = archive.resolve_paths()?;
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[&hash_path("def/city.sii")], "def/city.sii");
+        assert_eq!(paths[&hash_path("/def/")], "def");
+        assert!(!paths.contains_key(&0x1234));
+
+        let mut out = Vec::new();
+        archive.open_path("/def/city.sii")?.read_to_end(&mut out)?;
+        assert_eq!(out, city);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn entries_can_be_read_concurrently() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Archive>();
+
+        let entries = (0..16u64)
+            .map(|i| {
+                let data = format!("entry {i} ").repeat(64).into_bytes();
+                (i, 2, crc32fast::hash(&data), data.len() as u32, zlib(&data))
+            })
+            .collect::<Vec<_>>();
+        let path = write_archive("threads", &entries)?;
+        let archive = Archive::load_from_path(path.to_str().unwrap())?;
+
+        // Interleave two readers on one thread, then fan out across threads.
+        let mut a = archive.open_entry(0)?;
+        let mut b = archive.open_entry(1)?;
+        let mut buf = [0u8; 8];
+        a.read_exact(&mut buf)?;
+        b.read_exact(&mut buf)?;
+        a.read_exact(&mut buf)?;
+        assert_eq!(&buf, b"entry 0 ");
+
+        std::thread::scope(|scope| {
+            let archive = &archive;
+            let handles = (0..16u64)
+                .map(|i| {
+                    scope.spawn(move || -> Result<Vec<u8>> {
+                        let mut out = Vec::new();
+                        archive.open_entry(i)?.with_crc32_check().read_to_end(&mut out)?;
+                        Ok(out)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for (i, h) in handles.into_iter().enumerate() {
+                let out = h.join().expect("reader thread panicked")?;
+                assert_eq!(out, format!("entry {i} ").repeat(64).into_bytes());
             }
+            Ok::<(), anyhow::Error>(())
+        })?;
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn entries_are_seekable() -> Result<()> {
+        let data = (0..=255u8).cycle().take(4096).collect::<Vec<_>>();
+        let crc = crc32fast::hash(&data);
+        let path = write_archive(
+            "seek",
+            &[
+                (1, 0, crc, data.len() as u32, data.clone()),
+                (2, 2, crc, data.len() as u32, zlib(&data)),
+            ],
+        )?;
+        let archive = Archive::load_from_path(path.to_str().unwrap())?;
+
+        for hash in [1, 2] {
+            let mut reader = archive.open_entry(hash)?;
+            assert_eq!(reader.size(), 4096);
+            assert_eq!(reader.is_compressed(), hash == 2);
+
+            let mut buf = [0u8; 4];
+            reader.seek(SeekFrom::Start(1000))?;
+            reader.read_exact(&mut buf)?;
+            assert_eq!(buf, data[1000..1004]);
+
+            reader.seek(SeekFrom::Current(-500))?;
+            reader.read_exact(&mut buf)?;
+            assert_eq!(buf, data[504..508]);
+
+            assert_eq!(reader.seek(SeekFrom::End(-2))?, 4094);
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest)?;
+            assert_eq!(rest, data[4094..]);
+
+            assert!(reader.seek(SeekFrom::End(1)).is_err());
+            assert!(reader.seek(SeekFrom::Current(-5000)).is_err());
         }
+
+        std::fs::remove_file(path)?;
+        Ok(())
     }
-}
\ No newline at end of file
+}
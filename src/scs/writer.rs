@@ -0,0 +1,252 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Result};
+use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+use flate2::{write::ZlibEncoder, Compression};
+
+use super::{hash_path, Archive};
+
+/// Builds a HashFS archive that `Archive::load_from_file` (and the game) can
+/// read.  Directory entries are generated from the file paths, so every file
+/// is reachable by walking the tree from the root.
+pub struct ArchiveWriter {
+    files: BTreeMap<String, Vec<u8>>,
+    compress: bool,
+}
+
+#[derive(Default)]
+struct DirContents {
+    subdirs: BTreeSet<String>,
+    files: BTreeSet<String>,
+}
+
+struct PendingEntry {
+    hash: u64,
+    entry_type: u32,
+    crc32: u32,
+    size: u32,
+    data: Vec<u8>,
+}
+
+impl ArchiveWriter {
+    const HEADER_LEN: u32 = 20;
+
+    /// If `compress` is set, entries are zlib-compressed whenever that
+    /// actually makes them smaller.
+    pub fn new(compress: bool) -> Self {
+        Self {
+            files: BTreeMap::new(),
+            compress,
+        }
+    }
+
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        let path = path.trim_matches('/');
+        if path.is_empty() || path.split('/').any(|p| p.is_empty() || p == "." || p == "..") {
+            bail!("invalid archive path '{path}'");
+        }
+        if self.files.contains_key(path) {
+            bail!("duplicate archive path '{path}'");
+        }
+
+        self.files.insert(path.to_owned(), data);
+        Ok(())
+    }
+
+    /// Recursively adds every file under `root`, named by its path relative to
+    /// `root`.
+    pub fn add_dir(&mut self, root: &Path) -> Result<()> {
+        self.add_dir_inner(root, "")
+    }
+
+    fn add_dir_inner(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry
+                .file_name()
+                .into_string()
+                .map_err(|n| anyhow!("non-UTF-8 filename {n:?}"))?;
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+
+            if entry.file_type()?.is_dir() {
+                self.add_dir_inner(&entry.path(), &path)?;
+            } else {
+                self.add_file(&path, fs::read(entry.path())?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists the subdirectories and files of every directory in the archive,
+    /// including the root (the empty path).
+    fn directories(&self) -> Result<BTreeMap<String, DirContents>> {
+        let mut dirs: BTreeMap<String, DirContents> = BTreeMap::new();
+        dirs.insert(String::new(), Default::default());
+
+        for path in self.files.keys() {
+            let (mut parent, file_name) = match path.rsplit_once('/') {
+                Some((parent, name)) => (parent, name),
+                None => ("", path.as_str()),
+            };
+            dirs.entry(parent.to_owned()).or_default().files.insert(file_name.to_owned());
+
+            while !parent.is_empty() {
+                let (grandparent, dir_name) = parent.rsplit_once('/').unwrap_or(("", parent));
+                dirs.entry(grandparent.to_owned()).or_default().subdirs.insert(dir_name.to_owned());
+                parent = grandparent;
+            }
+        }
+
+        for (dir, _) in dirs.iter().filter(|(d, _)| !d.is_empty()) {
+            if self.files.contains_key(dir) {
+                bail!("'{dir}' is both a file and a directory");
+            }
+        }
+
+        Ok(dirs)
+    }
+
+    fn pending_entry(&self, path: &str, is_dir: bool, data: &[u8]) -> Result<PendingEntry> {
+        let size = u32::try_from(data.len()).map_err(|_| anyhow!("'{path}' is too large"))?;
+        let crc32 = crc32fast::hash(data);
+        let mut entry_type = if is_dir { 1 } else { 0 };
+        let mut stored = data.to_vec();
+
+        if self.compress {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+            encoder.write_all(data)?;
+            let compressed = encoder.finish()?;
+            if compressed.len() < data.len() {
+                entry_type |= 2;
+                stored = compressed;
+            }
+        }
+
+        Ok(PendingEntry {
+            hash: hash_path(path),
+            entry_type,
+            crc32,
+            size,
+            data: stored,
+        })
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        let mut entries = Vec::new();
+        for (dir, contents) in self.directories()? {
+            let listing = contents
+                .subdirs
+                .iter()
+                .map(|d| format!("*{d}"))
+                .chain(contents.files.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("\n");
+            entries.push(self.pending_entry(&dir, true, listing.as_bytes())?);
+        }
+        for (path, data) in &self.files {
+            entries.push(self.pending_entry(path, false, data)?);
+        }
+
+        let mut seen: HashMap<u64, usize> = HashMap::with_capacity(entries.len());
+        for (i, e) in entries.iter().enumerate() {
+            if let Some(j) = seen.insert(e.hash, i) {
+                bail!("hash collision between entries {j} and {i} ({:X})", e.hash);
+            }
+        }
+        // The game binary searches the entry table, so keep it sorted.
+        entries.sort_by_key(|e| e.hash);
+
+        let data_len: u64 = entries.iter().map(|e| e.data.len() as u64).sum();
+        let entry_offset = u32::try_from(Self::HEADER_LEN as u64 + data_len)
+            .map_err(|_| anyhow!("archive is too large"))?;
+
+        w.write_u32::<BigEndian>(Archive::SCS_SIGNATURE)?;
+        w.write_u32::<LittleEndian>(1)?;
+        w.write_u32::<BigEndian>(Archive::CITYHASH_MARKER)?;
+        w.write_u32::<LittleEndian>(entries.len() as u32)?;
+        w.write_u32::<LittleEndian>(entry_offset)?;
+        for e in &entries {
+            w.write_all(&e.data)?;
+        }
+
+        let mut offset = Self::HEADER_LEN;
+        for e in &entries {
+            w.write_u64::<LittleEndian>(e.hash)?;
+            w.write_u32::<LittleEndian>(offset)?;
+            w.write_u32::<LittleEndian>(0)?;
+            w.write_u32::<LittleEndian>(e.entry_type)?;
+            w.write_u32::<LittleEndian>(e.crc32)?;
+            w.write_u32::<LittleEndian>(e.size)?;
+            w.write_u32::<LittleEndian>(e.data.len() as u32)?;
+            offset += e.data.len() as u32;
+        }
+        debug_assert_eq!(offset, entry_offset);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use anyhow::Result;
+
+    use super::ArchiveWriter;
+    use crate::{
+        scs::{hash_path, Archive, DirListing},
+        test_util::temp_path,
+    };
+
+    #[test]
+    fn round_trip_through_archive() -> Result<()> {
+        let city = b"SiiNunit\n{\n}\n".repeat(16);
+        let mut writer = ArchiveWriter::new(true);
+        writer.add_file("manifest.sii", b"x".to_vec())?;
+        writer.add_file("def/city.sii", city.clone())?;
+        writer.add_file("/def/city/berlin.sui", b"berlin".to_vec())?;
+        assert!(writer.add_file("def/city.sii", Vec::new()).is_err());
+        assert!(writer.add_file("def/../city.sii", Vec::new()).is_err());
+
+        let path = temp_path("writer.scs");
+        writer.write(&mut std::fs::File::create(&path)?)?;
+        let archive = Archive::load_from_path(path.to_str().unwrap())?;
+
+        assert!(archive.describe_entry(hash_path("def/city.sii")).unwrap().entry_type.is_compressed());
+        assert!(!archive.describe_entry(hash_path("manifest.sii")).unwrap().entry_type.is_compressed());
+        assert_eq!(
+            archive.read_dir("def")?,
+            vec![
+                DirListing { name: "city".to_owned(), is_dir: true },
+                DirListing { name: "city.sii".to_owned(), is_dir: false },
+            ]
+        );
+        assert_eq!(archive.resolve_paths()?.len(), 6);
+
+        let mut out = Vec::new();
+        archive.open_path("def/city.sii")?.with_crc32_check().read_to_end(&mut out)?;
+        assert_eq!(out, city);
+
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_file_and_directory_with_same_name() -> Result<()> {
+        let mut writer = ArchiveWriter::new(false);
+        writer.add_file("def", Vec::new())?;
+        writer.add_file("def/city.sii", Vec::new())?;
+        assert!(writer.write(&mut Vec::new()).is_err());
+        Ok(())
+    }
+}
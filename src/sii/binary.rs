@@ -1,11 +1,13 @@
 use anyhow::{anyhow, bail, Result};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use flate2::read::ZlibDecoder;
-use std::{collections::HashMap, io::Read, fs::File};
+use std::{collections::{HashMap, HashSet}, io::{Read, Write}, fs::File};
 
 use crate::crypt::sii::Decryptor;
 
-use super::value::{OrdinalStringTable, ReadFrom, Value, ID, Struct};
+use super::value::{OrdinalStringTable, ReadFrom, Value, ID, Struct, WriteTo};
+
+const SII_SIGNATURE: u32 = 0x49495342;
 
 #[derive(Clone, Debug)]
 pub struct StructFieldDef {
@@ -28,15 +30,14 @@ pub enum Block {
 
 pub struct Parser<R: Read> {
     reader: R,
+    version: u32,
     struct_defs: HashMap<u32, Schema>,
 }
 
 impl<R: Read> Parser<R> {
-    const SII_SIGNATURE: u32 = 0x49495342;
-
     pub fn new(mut reader: R) -> Result<Self> {
         let signature = reader.read_u32::<LittleEndian>()?;
-        if signature != Self::SII_SIGNATURE {
+        if signature != SII_SIGNATURE {
             bail!("invalid signature: {signature:X}")
         }
 
@@ -47,10 +48,15 @@ impl<R: Read> Parser<R> {
 
         Ok(Self {
             reader,
+            version,
             struct_defs: HashMap::new(),
         })
     }
 
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
     pub fn next_block(&mut self) -> Result<Option<Block>> {
         let block_type = self.reader.read_u32::<LittleEndian>()?;
 
@@ -124,6 +130,95 @@ impl<R: Read> Parser<R> {
     }
 }
 
+/// Writes blocks in the format read by `Parser`.  Schemas are written the
+/// first time a block uses them, as the game does.
+pub struct Writer<W: Write> {
+    writer: W,
+    written_schemas: HashSet<u32>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(mut writer: W, version: u32) -> Result<Self> {
+        if version != 2 && version != 3 {
+            bail!("unsupported version: {version}")
+        }
+
+        writer.write_u32::<LittleEndian>(SII_SIGNATURE)?;
+        writer.write_u32::<LittleEndian>(version)?;
+
+        Ok(Self {
+            writer,
+            written_schemas: HashSet::new(),
+        })
+    }
+
+    fn write_schema(&mut self, schema: &Schema) -> Result<()> {
+        self.writer.write_u32::<LittleEndian>(0)?;
+        true.write_to(&mut self.writer)?;
+        schema.id.write_to(&mut self.writer)?;
+        schema.name.write_to(&mut self.writer)?;
+
+        for field in &schema.fields {
+            field.value_type.write_to(&mut self.writer)?;
+            field.name.write_to(&mut self.writer)?;
+            if field.value_type == 0x37 {
+                field
+                    .ordinal_table
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("missing ordinal table for {}", field.name))?
+                    .write_to(&mut self.writer)?;
+            }
+        }
+
+        self.writer.write_u32::<LittleEndian>(0)?;
+        self.written_schemas.insert(schema.id);
+        Ok(())
+    }
+
+    /// Writes `block`, whose fields must be exactly those of `schema`.
+    pub fn write_struct(&mut self, schema: &Schema, block: &Struct) -> Result<()> {
+        if schema.id == 0 {
+            bail!("schema ID 0 is reserved");
+        }
+        if schema.name != block.struct_name {
+            bail!(
+                "cannot write {} {:?} with the {} schema",
+                block.struct_name,
+                block.id,
+                schema.name
+            );
+        }
+        if schema.fields.len() != block.fields.len() {
+            bail!("{} {:?} does not have the fields of its schema", block.struct_name, block.id);
+        }
+        if !self.written_schemas.contains(&schema.id) {
+            self.write_schema(schema)?;
+        }
+
+        self.writer.write_u32::<LittleEndian>(schema.id)?;
+        block.id.write_to(&mut self.writer)?;
+        for field in &schema.fields {
+            let context = || format!("{} in {} {:?}", field.name, block.struct_name, block.id);
+            let value = block
+                .fields
+                .get(&field.name)
+                .ok_or_else(|| anyhow!("missing field {}", context()))?;
+            value
+                .write_to(&mut self.writer, field.value_type, field.ordinal_table.as_ref())
+                .map_err(|e| anyhow!("cannot write {}: {e}", context()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the end-of-file marker and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.write_u32::<LittleEndian>(0)?;
+        false.write_to(&mut self.writer)?;
+        Ok(self.writer)
+    }
+}
+
 impl Parser<ZlibDecoder<VecRead>> {
     pub fn new_from_save(path: &str) -> Result<Self> {
         let enc_file = File::open(path)?;
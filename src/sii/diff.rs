@@ -0,0 +1,516 @@
+//! Structural comparison of two saves.
+//!
+//! Named blocks are matched by ID.  Nameless IDs are reassigned every time the
+//! game saves, so nameless blocks are matched by where they are referenced
+//! from instead: the referencing block's own match, the field and the array
+//! position.  A nameless block nothing references is matched by struct name
+//! if it is the only one of its kind (e.g. `economy`).
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+};
+
+use super::{
+    game::GameSave,
+    value::{Struct, Value, ID},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delta {
+    Integer(i128),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldChange {
+    /// A scalar (or non-string array) changed; numbers also get the delta.
+    Value {
+        old: Value,
+        new: Value,
+        delta: Option<Delta>,
+    },
+    /// A string or ID array changed, compared as a multiset.
+    Set {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    /// A string or ID array has the same elements in a different order.
+    Reordered,
+    /// The field only exists in the new block (e.g. after a game update).
+    Added(Value),
+    Removed(Value),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub field: String,
+    pub change: FieldChange,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockDiff {
+    pub id: ID,
+    pub struct_name: String,
+    /// Set if the block kept its ID but changed type.
+    pub old_struct_name: Option<String>,
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Differences between two saves.  Everything is sorted by ID, then by field
+/// name; changed blocks by their ID in the new save.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaveDiff {
+    pub added: Vec<(ID, String)>,
+    pub removed: Vec<(ID, String)>,
+    pub changed: Vec<BlockDiff>,
+    /// Nameless blocks of the old and new save that cannot be matched up,
+    /// e.g. unreferenced ones of a struct name that occurs more than once.
+    /// They are not compared, so they do not count as differences.
+    pub unmatched_old: Vec<(ID, String)>,
+    pub unmatched_new: Vec<(ID, String)>,
+}
+
+impl SaveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Where a block sits in its save, which stays the same between saves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum BlockKey {
+    Named(ID),
+    /// The only unreferenced nameless block of this struct name.
+    Root(String),
+    /// Referenced from a field (and array position) of another block.
+    Field(Box<BlockKey>, String, Option<usize>),
+}
+
+/// Keys for every block of `save` that has one.
+fn block_keys(save: &GameSave) -> HashMap<&ID, BlockKey> {
+    let mut roots: HashMap<&str, usize> = HashMap::new();
+    for (id, block) in save.iter_blocks() {
+        if matches!(id, ID::Nameless(_)) && save.references_to(id).is_empty() {
+            *roots.entry(block.struct_name.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut memo = HashMap::new();
+    save.iter_blocks()
+        .filter_map(|(id, _)| {
+            let key = key_of(save, id, &roots, &mut memo, &mut HashSet::new(), &mut false)?;
+            Some((id, key))
+        })
+        .collect()
+}
+
+/// The key of `id`, from the smallest key among its references so that both
+/// saves pick the same one.  Nameless blocks only referenced from a cycle of
+/// nameless blocks have none.
+///
+/// `cut` is set if a reference was skipped for leading back into `visiting`;
+/// such keys depend on where the walk started, so they are not memoized.
+fn key_of<'a>(
+    save: &'a GameSave,
+    id: &'a ID,
+    roots: &HashMap<&str, usize>,
+    keys: &mut HashMap<&'a ID, Option<BlockKey>>,
+    visiting: &mut HashSet<&'a ID>,
+    cut: &mut bool,
+) -> Option<BlockKey> {
+    if let ID::Named(_) = id {
+        return Some(BlockKey::Named(id.clone()));
+    }
+    if let Some(key) = keys.get(id) {
+        return key.clone();
+    }
+    if !visiting.insert(id) {
+        *cut = true;
+        return None;
+    }
+
+    let mut cycle = false;
+    let references = save.references_to(id);
+    let key = if references.is_empty() {
+        save.get_block_by_id(id)
+            .filter(|b| roots.get(b.struct_name.as_str()) == Some(&1))
+            .map(|b| BlockKey::Root(b.struct_name.clone()))
+    } else {
+        references
+            .iter()
+            .filter_map(|r| {
+                let parent = key_of(save, &r.from, roots, keys, visiting, &mut cycle)?;
+                Some(BlockKey::Field(Box::new(parent), r.field.clone(), r.index))
+            })
+            .min()
+    };
+
+    visiting.remove(id);
+    if cycle {
+        *cut = true;
+    } else {
+        keys.insert(id, key.clone());
+    }
+    key
+}
+
+pub fn diff_saves(old: &GameSave, new: &GameSave) -> SaveDiff {
+    let old_keys = block_keys(old);
+    let new_keys = block_keys(new);
+    let old_by_key: HashMap<&BlockKey, &ID> = old_keys.iter().map(|(id, k)| (k, *id)).collect();
+    let new_by_key: HashMap<&BlockKey, &ID> = new_keys.iter().map(|(id, k)| (k, *id)).collect();
+    let old_blocks: BTreeMap<&ID, &Struct> = old.iter_blocks().collect();
+    let new_blocks: BTreeMap<&ID, &Struct> = new.iter_blocks().collect();
+    let mut out = SaveDiff::default();
+
+    // Old nameless IDs that now have another ID, so references to them are
+    // not reported as changes.
+    let renamed: HashMap<&ID, &ID> = old_keys
+        .iter()
+        .filter_map(|(id, key)| Some((*id, *new_by_key.get(key)?)))
+        .filter(|(old_id, new_id)| old_id != new_id)
+        .collect();
+
+    for (id, block) in &old_blocks {
+        let entry = ((*id).clone(), block.struct_name.clone());
+        match old_keys.get(id) {
+            None => out.unmatched_old.push(entry),
+            Some(key) if !new_by_key.contains_key(key) => out.removed.push(entry),
+            Some(_) => {}
+        }
+    }
+
+    for (id, block) in &new_blocks {
+        let entry = ((*id).clone(), block.struct_name.clone());
+        let Some(key) = new_keys.get(id) else {
+            out.unmatched_new.push(entry);
+            continue;
+        };
+        match old_by_key.get(key) {
+            None => out.added.push(entry),
+            Some(old_id) => {
+                let old_block = rename_ids(old_blocks[old_id], &renamed);
+                if let Some(d) = diff_blocks(&old_block, block) {
+                    out.changed.push(d);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// `block` with references to renamed blocks replaced by their new IDs.
+fn rename_ids<'a>(block: &'a Struct, renamed: &HashMap<&ID, &ID>) -> Cow<'a, Struct> {
+    let is_renamed = |v: &Value| match v {
+        Value::ID(id) => renamed.contains_key(id),
+        Value::IDArray(ids) => ids.iter().any(|id| renamed.contains_key(id)),
+        _ => false,
+    };
+    if !block.fields.values().any(is_renamed) {
+        return Cow::Borrowed(block);
+    }
+
+    let rename = |id: &mut ID| {
+        if let Some(new_id) = renamed.get(id) {
+            *id = (*new_id).clone();
+        }
+    };
+    let mut block = block.clone();
+    for value in block.fields.values_mut() {
+        match value {
+            Value::ID(id) => rename(id),
+            Value::IDArray(ids) => ids.iter_mut().for_each(rename),
+            _ => {}
+        }
+    }
+    Cow::Owned(block)
+}
+
+/// Compares two versions of the same block; `None` if they are identical.
+pub fn diff_blocks(old: &Struct, new: &Struct) -> Option<BlockDiff> {
+    let names: BTreeSet<&String> = old.fields.keys().chain(new.fields.keys()).collect();
+    let mut fields = Vec::new();
+
+    for name in names {
+        let change = match (old.fields.get(name), new.fields.get(name)) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(a), Some(b)) => diff_values(a, b),
+            (None, Some(b)) => FieldChange::Added(b.clone()),
+            (Some(a), None) => FieldChange::Removed(a.clone()),
+            (None, None) => unreachable!(),
+        };
+        fields.push(FieldDiff {
+            field: name.clone(),
+            change,
+        });
+    }
+
+    let old_struct_name = (old.struct_name != new.struct_name).then(|| old.struct_name.clone());
+    if fields.is_empty() && old_struct_name.is_none() {
+        return None;
+    }
+
+    Some(BlockDiff {
+        id: new.id.clone(),
+        struct_name: new.struct_name.clone(),
+        old_struct_name,
+        fields,
+    })
+}
+
+fn diff_values(old: &Value, new: &Value) -> FieldChange {
+    if let (Some(a), Some(b)) = (string_elements(old), string_elements(new)) {
+        // How many more times each element occurs in `b` than in `a`.
+        let mut extra: HashMap<&str, usize> = HashMap::new();
+        for e in &b {
+            *extra.entry(e).or_default() += 1;
+        }
+        let mut removed = Vec::new();
+        for e in &a {
+            match extra.get_mut(e.as_str()) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => removed.push(e.clone()),
+            }
+        }
+
+        // Keep additions in the order they appear in the new array.
+        let mut added = Vec::new();
+        for e in &b {
+            let n = extra.get_mut(e.as_str()).expect("counted above");
+            if *n > 0 {
+                *n -= 1;
+                added.push(e.clone());
+            }
+        }
+        if removed.is_empty() && added.is_empty() {
+            return FieldChange::Reordered;
+        }
+        return FieldChange::Set { added, removed };
+    }
+
+    let delta = match (old.as_integer(), new.as_integer(), old, new) {
+        (Some(a), Some(b), _, _) => Some(Delta::Integer(b - a)),
+        (_, _, Value::Single(a), Value::Single(b)) => Some(Delta::Float(*b as f64 - *a as f64)),
+        _ => None,
+    };
+
+    FieldChange::Value {
+        old: old.clone(),
+        new: new.clone(),
+        delta,
+    }
+}
+
+/// String and ID arrays, as strings.  In the text format an empty array is
+/// just a `0` length, which counts as an empty array of anything.
+fn string_elements(value: &Value) -> Option<Vec<String>> {
+    match value {
+        Value::StringArray(a) => Some(a.clone()),
+        Value::EncodedStringArray(a) => Some(a.iter().map(|s| s.to_string()).collect()),
+        Value::IDArray(a) => Some(a.iter().map(|id| id.to_string()).collect()),
+        Value::UInt64(0) => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+/// A short, human-readable rendering of a value.
+pub fn format_value(value: &Value) -> String {
+    fn list<T, F: Fn(&T) -> String>(items: &[T], f: F) -> String {
+        format!("[{}]", items.iter().map(f).collect::<Vec<_>>().join(", "))
+    }
+
+    match value {
+        Value::String(s) | Value::OrdinalString(s) => format!("{s:?}"),
+        Value::StringArray(a) => list(a, |s| format!("{s:?}")),
+        Value::EncodedString(s) => s.to_string(),
+        Value::EncodedStringArray(a) => list(a, |s| s.to_string()),
+        Value::Single(v) => v.to_string(),
+        Value::SingleArray(a) => list(a, |v| v.to_string()),
+        Value::Int32(v) => v.to_string(),
+        Value::Int32Array(a) => list(a, |v| v.to_string()),
+        Value::UInt32(v) => v.to_string(),
+        Value::UInt32Array(a) => list(a, |v| v.to_string()),
+        Value::UInt16(v) => v.to_string(),
+        Value::UInt16Array(a) => list(a, |v| v.to_string()),
+        Value::Int64(v) => v.to_string(),
+        Value::Int64Array(a) => list(a, |v| v.to_string()),
+        Value::UInt64(v) => v.to_string(),
+        Value::UInt64Array(a) => list(a, |v| v.to_string()),
+        Value::ByteBool(v) => v.to_string(),
+        Value::ByteBoolArray(a) => list(a, |v| v.to_string()),
+        Value::ID(id) if id.is_null() => "null".to_owned(),
+        Value::ID(id) => id.to_string(),
+        Value::IDArray(a) => list(a, |id| id.to_string()),
+        v => format!("{v:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        sii::{
+            game::GameSave,
+            value::{EncodedString, Struct, Value, ID},
+        },
+        test_util::{block, nameless},
+    };
+
+    use super::{diff_saves, Delta, FieldChange};
+
+    fn named(id: &str, struct_name: &str, fields: Vec<(&str, Value)>) -> Struct {
+        block(ID::try_from(id).unwrap(), struct_name, fields)
+    }
+
+    fn cities(names: &[&str]) -> Value {
+        Value::EncodedStringArray(names.iter().map(|n| EncodedString::try_from(*n).unwrap()).collect())
+    }
+
+    #[test]
+    fn reports_block_and_field_changes() {
+        let old: GameSave = [
+            nameless(
+                1,
+                "economy",
+                vec![
+                    ("experience_points", Value::UInt32(1000)),
+                    ("visited_cities", cities(&["berlin", "calais"])),
+                    ("garages", Value::IDArray(vec![ID::Nameless(5), ID::Nameless(6)])),
+                    ("game_time", Value::UInt32(7)),
+                ],
+            ),
+            nameless(2, "bank", vec![("money_account", Value::Int64(500))]),
+            named("offer.calais", "job_offer_data", vec![]),
+        ]
+        .into_iter()
+        .collect();
+        let new: GameSave = [
+            nameless(
+                1,
+                "economy",
+                vec![
+                    ("experience_points", Value::UInt32(900)),
+                    ("visited_cities", cities(&["berlin", "dresden", "calais"])),
+                    ("garages", Value::IDArray(vec![ID::Nameless(6), ID::Nameless(5)])),
+                    ("game_time", Value::UInt32(7)),
+                ],
+            ),
+            nameless(2, "bank", vec![("money_account", Value::Int64(-250))]),
+            named("offer.dresden", "job_offer_data", vec![]),
+        ]
+        .into_iter()
+        .collect();
+
+        let diff = diff_saves(&old, &new);
+        let offer = |id: &str| (ID::try_from(id).unwrap(), "job_offer_data".to_owned());
+        assert_eq!(diff.added, vec![offer("offer.dresden")]);
+        assert_eq!(diff.removed, vec![offer("offer.calais")]);
+        assert_eq!(diff.changed.len(), 2);
+
+        let bank = diff.changed.iter().find(|b| b.struct_name == "bank").unwrap();
+        assert_eq!(
+            bank.fields[0].change,
+            FieldChange::Value {
+                old: Value::Int64(500),
+                new: Value::Int64(-250),
+                delta: Some(Delta::Integer(-750)),
+            }
+        );
+
+        let econ = diff.changed.iter().find(|b| b.struct_name == "economy").unwrap();
+        let fields: Vec<_> = econ.fields.iter().map(|f| (f.field.as_str(), &f.change)).collect();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[1].0, "garages");
+        assert_eq!(*fields[1].1, FieldChange::Reordered);
+        assert_eq!(
+            *fields[2].1,
+            FieldChange::Set {
+                added: vec!["dresden".to_owned()],
+                removed: vec![],
+            }
+        );
+
+        assert!(diff_saves(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn matches_nameless_blocks_by_referrer() {
+        let ids = |ids: &[u64]| Value::IDArray(ids.iter().map(|i| ID::Nameless(*i)).collect());
+        let old: GameSave = [
+            nameless(1, "economy", vec![("player", Value::ID(ID::Nameless(2)))]),
+            nameless(2, "player", vec![("trucks", ids(&[3, 4]))]),
+            nameless(
+                3,
+                "vehicle",
+                vec![("odometer", Value::UInt32(10)), ("accessories", ids(&[7]))],
+            ),
+            nameless(4, "vehicle", vec![("odometer", Value::UInt32(20))]),
+            nameless(7, "vehicle_accessory", vec![("parent", Value::ID(ID::Nameless(3)))]),
+            nameless(5, "job_offer_data", vec![]),
+            nameless(6, "job_offer_data", vec![]),
+        ]
+        .into_iter()
+        .collect();
+        let new: GameSave = [
+            nameless(11, "economy", vec![("player", Value::ID(ID::Nameless(12)))]),
+            nameless(12, "player", vec![("trucks", ids(&[13, 14, 15]))]),
+            nameless(
+                13,
+                "vehicle",
+                vec![("odometer", Value::UInt32(10)), ("accessories", ids(&[18]))],
+            ),
+            nameless(18, "vehicle_accessory", vec![("parent", Value::ID(ID::Nameless(13)))]),
+            nameless(14, "vehicle", vec![("odometer", Value::UInt32(25))]),
+            nameless(15, "vehicle", vec![("odometer", Value::UInt32(0))]),
+            nameless(16, "job_offer_data", vec![]),
+            nameless(17, "job_offer_data", vec![]),
+        ]
+        .into_iter()
+        .collect();
+
+        let diff = diff_saves(&old, &new);
+        assert_eq!(diff.added, vec![(ID::Nameless(15), "vehicle".to_owned())]);
+        assert!(diff.removed.is_empty());
+        let unmatched: Vec<_> = diff.unmatched_new.iter().map(|(id, _)| id.clone()).collect();
+        assert_eq!(unmatched, vec![ID::Nameless(16), ID::Nameless(17)]);
+        assert_eq!(diff.unmatched_old.len(), 2);
+
+        // The economy's player reference only changed its nameless ID.
+        let changed: Vec<_> = diff.changed.iter().map(|b| (&b.id, &b.fields[0].change)).collect();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(
+            changed[0],
+            (
+                &ID::Nameless(12),
+                &FieldChange::Set {
+                    added: vec![ID::Nameless(15).to_string()],
+                    removed: vec![],
+                }
+            )
+        );
+        assert_eq!(changed[1].0, &ID::Nameless(14));
+        assert!(diff_saves(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn counts_repeated_elements() {
+        let list =
+            |names: &[&str]| Value::StringArray(names.iter().map(|n| n.to_string()).collect());
+        let old: GameSave = [nameless(1, "economy", vec![("log", list(&["a", "b", "a", "c"]))])]
+            .into_iter()
+            .collect();
+        let new: GameSave = [nameless(1, "economy", vec![("log", list(&["c", "a", "d", "d", "b"]))])]
+            .into_iter()
+            .collect();
+
+        let diff = diff_saves(&old, &new);
+        assert_eq!(
+            diff.changed[0].fields[0].change,
+            FieldChange::Set {
+                added: vec!["d".to_owned(), "d".to_owned()],
+                removed: vec!["a".to_owned()],
+            }
+        );
+    }
+}
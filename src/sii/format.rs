@@ -0,0 +1,88 @@
+use std::{fs, io::Read, path::Path};
+
+use anyhow::{bail, Result};
+use flate2::read::ZlibDecoder;
+
+use crate::crypt::{sii::Decryptor, threenk};
+
+use super::{
+    binary::{self, Block},
+    text,
+    value::Struct,
+};
+
+/// The encodings the game uses for sii files.  Saves are usually encrypted
+/// binary, but `g_save_format` can make the game write plain binary or text
+/// instead.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// `ScsC`: AES encrypted and usually zlib compressed.
+    Encrypted,
+    /// `BSII`
+    Binary,
+    /// `SiiNunit`
+    Text,
+    /// `3nK`: XOR encrypted text, as used for locale files.
+    ThreeNK,
+}
+
+impl Format {
+    pub fn detect(data: &[u8]) -> Result<Self> {
+        let text_start = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match data.get(..4) {
+            Some(b"ScsC") => Ok(Self::Encrypted),
+            Some(b"BSII") => Ok(Self::Binary),
+            Some(b"3nK\x01") => Ok(Self::ThreeNK),
+            _ if text_start.starts_with(b"SiiNunit") => Ok(Self::Text),
+            _ => bail!("unrecognized sii file format"),
+        }
+    }
+}
+
+/// Reads every block of a sii file in any `Format`, skipping schemas.
+pub fn read_structs(data: &[u8]) -> Result<Vec<Struct>> {
+    match Format::detect(data)? {
+        Format::Encrypted => {
+            let decrypted = Decryptor::new(data).decrypt()?;
+            if decrypted.first() == Some(&0x78) {
+                let mut inflated = Vec::new();
+                ZlibDecoder::new(decrypted.as_slice()).read_to_end(&mut inflated)?;
+                read_structs(&inflated)
+            } else {
+                read_structs(&decrypted)
+            }
+        }
+        Format::Binary => {
+            let mut parser = binary::Parser::new(data)?;
+            let mut out = Vec::new();
+            while let Some(block) = parser.next_block()? {
+                if let Block::Struct(s) = block {
+                    out.push(s);
+                }
+            }
+            Ok(out)
+        }
+        Format::Text => text::Parser::new_from_reader(data)?.collect(),
+        Format::ThreeNK => {
+            let mut decrypted = Vec::new();
+            threenk::Decryptor::new(data)?.read_to_end(&mut decrypted)?;
+            read_structs(&decrypted)
+        }
+    }
+}
+
+/// Reads the blocks of an included `.sui` file, which is text without the
+/// `SiiNunit` wrapper.
+pub fn read_fragment_structs(data: &[u8]) -> Result<Vec<Struct>> {
+    if data.starts_with(b"3nK\x01") {
+        let mut decrypted = Vec::new();
+        threenk::Decryptor::new(data)?.read_to_end(&mut decrypted)?;
+        return read_fragment_structs(&decrypted);
+    }
+
+    text::Parser::new_fragment(text::Lexer::new(data.bytes().peekable())).collect()
+}
+
+pub fn read_structs_from_path(path: &Path) -> Result<Vec<Struct>> {
+    read_structs(&fs::read(path)?)
+}
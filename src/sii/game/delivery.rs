@@ -0,0 +1,239 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::sii::value::{Struct, ID};
+
+use super::{expect_struct_name, FromGameSave, GameSave};
+
+/// How the job was taken, from the first delivery log param.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum JobType {
+    QuickJob,
+    FreightMarket,
+    CargoMarket,
+    ExternalContract,
+    Other(u32),
+}
+
+impl From<u32> for JobType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => Self::QuickJob,
+            1 => Self::FreightMarket,
+            2 => Self::CargoMarket,
+            3 => Self::ExternalContract,
+            x => Self::Other(x),
+        }
+    }
+}
+
+/// One finished job from the `delivery_log`.
+///
+/// The entry itself is just a list of string `params`; the positions below
+/// are what has been observed in saves.  Entries from older saves have been
+/// seen with fewer params, and fields past the end of an entry are `None`;
+/// that assumes later versions only appended params, which has not been
+/// checked against every game version.  The raw params are kept for anything
+/// not decoded here.
+#[derive(Debug, Clone)]
+pub struct DeliveryLogEntry {
+    pub id: ID,
+    pub job_type: JobType,
+    pub source: ID,
+    pub target: ID,
+    pub cargo: ID,
+    /// In-game minutes since the profile was started.
+    pub started_at: Option<u32>,
+    pub finished_at: Option<u32>,
+    /// Planned distance of the job, in km.  Usually whole, but not always.
+    pub distance_km: Option<f32>,
+    pub auto_park: Option<bool>,
+    pub revenue: Option<i64>,
+    pub xp: Option<u32>,
+    /// Fraction of the cargo value lost to damage, 0.0 - 1.0.
+    pub cargo_damage: Option<f32>,
+    pub cargo_mass_kg: Option<f32>,
+    pub auto_load: Option<bool>,
+    pub params: Vec<String>,
+}
+
+/// Positions of the decoded fields in `params`.
+impl DeliveryLogEntry {
+    pub const JOB_TYPE: usize = 0;
+    pub const SOURCE: usize = 1;
+    pub const TARGET: usize = 2;
+    pub const CARGO: usize = 3;
+    pub const STARTED_AT: usize = 4;
+    pub const FINISHED_AT: usize = 5;
+    pub const DISTANCE_KM: usize = 6;
+    pub const AUTO_PARK: usize = 7;
+    pub const REVENUE: usize = 8;
+    pub const XP: usize = 9;
+    pub const CARGO_DAMAGE: usize = 10;
+    pub const CARGO_MASS_KG: usize = 11;
+    pub const AUTO_LOAD: usize = 12;
+}
+
+impl DeliveryLogEntry {
+    /// City token of the source company (`company.volatile.<company>.<city>`).
+    pub fn source_city(&self) -> Option<String> {
+        self.source.string_part(-1)
+    }
+
+    pub fn target_city(&self) -> Option<String> {
+        self.target.string_part(-1)
+    }
+
+    /// Company token of the source company, without the city.
+    pub fn source_company(&self) -> Option<String> {
+        self.source.string_part(-2)
+    }
+
+    pub fn target_company(&self) -> Option<String> {
+        self.target.string_part(-2)
+    }
+
+    /// Cargo token, without the `cargo.` prefix.
+    pub fn cargo_name(&self) -> Option<String> {
+        self.cargo.string_part(-1)
+    }
+
+    fn param(params: &[String], index: usize) -> Result<&str> {
+        params
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow!("missing delivery log param {index}"))
+    }
+
+    fn opt_param<T: FromStr>(params: &[String], index: usize) -> Result<Option<T>> {
+        match params.get(index) {
+            None => Ok(None),
+            Some(p) => p
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow!("cannot parse delivery log param {index} ('{p}')")),
+        }
+    }
+
+    fn opt_flag(params: &[String], index: usize) -> Result<Option<bool>> {
+        match params.get(index).map(String::as_str) {
+            None => Ok(None),
+            Some("0") | Some("false") => Ok(Some(false)),
+            Some("1") | Some("true") => Ok(Some(true)),
+            Some(p) => bail!("cannot parse delivery log param {index} ('{p}') as a flag"),
+        }
+    }
+}
+
+impl TryFrom<&Struct> for DeliveryLogEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Struct) -> Result<Self> {
+        expect_struct_name(value, "delivery_log_entry")?;
+        let params: Vec<String> = value.get_as("params")?;
+        let job_type: u32 = Self::param(&params, Self::JOB_TYPE)?
+            .parse()
+            .map_err(|_| anyhow!("cannot parse delivery log job type"))?;
+
+        Ok(Self {
+            id: value.id.clone(),
+            job_type: job_type.into(),
+            source: ID::try_from(Self::param(&params, Self::SOURCE)?)?,
+            target: ID::try_from(Self::param(&params, Self::TARGET)?)?,
+            cargo: ID::try_from(Self::param(&params, Self::CARGO)?)?,
+            started_at: Self::opt_param(&params, Self::STARTED_AT)?,
+            finished_at: Self::opt_param(&params, Self::FINISHED_AT)?,
+            distance_km: Self::opt_param(&params, Self::DISTANCE_KM)?,
+            auto_park: Self::opt_flag(&params, Self::AUTO_PARK)?,
+            revenue: Self::opt_param(&params, Self::REVENUE)?,
+            xp: Self::opt_param(&params, Self::XP)?,
+            cargo_damage: Self::opt_param(&params, Self::CARGO_DAMAGE)?,
+            cargo_mass_kg: Self::opt_param(&params, Self::CARGO_MASS_KG)?,
+            auto_load: Self::opt_flag(&params, Self::AUTO_LOAD)?,
+            params,
+        })
+    }
+}
+
+/// Every delivery log entry, oldest first.
+impl FromGameSave for Vec<DeliveryLogEntry> {
+    fn from_game_save(save: &GameSave) -> Result<Self> {
+        let dlog = save
+            .single_block_named("delivery_log")
+            .ok_or_else(|| anyhow!("missing delivery_log data"))?;
+
+        dlog.get_as::<Vec<ID>>("entries")?
+            .iter()
+            .map(|id| save.expect_block(id, "delivery_log_entry")?.try_into())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        sii::value::{Struct, Value},
+        test_util::nameless,
+    };
+
+    use super::{DeliveryLogEntry, JobType};
+
+    fn entry(params: &[&str]) -> Struct {
+        let params = params.iter().map(|p| p.to_string()).collect();
+        nameless(1, "delivery_log_entry", vec![("params", Value::StringArray(params))])
+    }
+
+    #[test]
+    fn decodes_params() -> Result<()> {
+        let e = DeliveryLogEntry::try_from(&entry(&[
+            "1",
+            "company.volatile.fle.calais",
+            "company.volatile.gnt.manchester",
+            "cargo.aircft_tyres",
+            "8000",
+            "8410",
+            "612",
+            "1",
+            "42150",
+            "1071",
+            "0.015",
+            "9200",
+            "0",
+        ]))?;
+
+        assert_eq!(e.job_type, JobType::FreightMarket);
+        assert_eq!(e.source_city().as_deref(), Some("calais"));
+        assert_eq!(e.target_company().as_deref(), Some("gnt"));
+        assert_eq!(e.cargo_name().as_deref(), Some("aircft_tyres"));
+        assert_eq!(e.distance_km, Some(612.0));
+        assert_eq!(e.auto_park, Some(true));
+        assert_eq!(e.revenue, Some(42150));
+        assert_eq!(e.cargo_damage, Some(0.015));
+        assert_eq!(e.auto_load, Some(false));
+        Ok(())
+    }
+
+    #[test]
+    fn older_entries_leave_newer_fields_empty() -> Result<()> {
+        let e = DeliveryLogEntry::try_from(&entry(&[
+            "0",
+            "company.volatile.tradeaux.berlin",
+            "company.volatile.posped.dresden",
+            "cargo.apples",
+            "100",
+            "250",
+            "190.5",
+        ]))?;
+
+        assert_eq!(e.job_type, JobType::QuickJob);
+        assert_eq!(e.distance_km, Some(190.5));
+        assert_eq!(e.auto_park, None);
+        assert_eq!(e.revenue, None);
+
+        assert!(DeliveryLogEntry::try_from(&entry(&["0", "company.volatile.a.b"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{anyhow, Result};
+
+use crate::sii::value::{Struct, ID};
+
+use super::{resolve_ref, FromGameSave, FromStruct, GameSave};
+
+/// The `economy` block, which is the root of most of the save: it references
+/// the player, bank, companies and garages.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "economy", singleton)]
+pub struct Economy {
+    #[sii(id)]
+    pub id: ID,
+    pub bank: ID,
+    pub player: ID,
+    pub companies: Vec<ID>,
+    pub garages: Vec<ID>,
+    /// In-game minutes since the profile was started.
+    pub game_time: u32,
+    pub experience_points: u32,
+    #[sii(flatten)]
+    pub skills: Skills,
+    pub total_distance: u32,
+    pub visited_cities: Vec<String>,
+    /// Number of visits, parallel to `visited_cities`.
+    pub visited_cities_count: Vec<u32>,
+    pub total_fuel_litres: u32,
+    pub total_fuel_price: i64,
+    pub gas_station_visit_count: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, FromStruct)]
+pub struct Skills {
+    /// Bitmask of the unlocked ADR classes.
+    pub adr: u32,
+    #[sii(rename = "long_dist")]
+    pub long_distance: u32,
+    pub heavy: u32,
+    pub fragile: u32,
+    pub urgent: u32,
+    pub mechanical: u32,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "player", singleton)]
+pub struct Player {
+    #[sii(id)]
+    pub id: ID,
+    pub hq_city: String,
+    pub assigned_truck: Option<ID>,
+    pub assigned_trailer: Option<ID>,
+    pub current_job: Option<ID>,
+    pub trucks: Vec<ID>,
+    pub trailers: Vec<ID>,
+    /// The first driver is the player's own `driver_player`; the rest are
+    /// hired `driver_ai`s.
+    pub drivers: Vec<ID>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "bank", singleton)]
+pub struct Bank {
+    #[sii(id)]
+    pub id: ID,
+    pub money_account: i64,
+    pub loan_limit: u32,
+    pub overdraft: bool,
+    #[sii(resolve)]
+    pub loans: Vec<BankLoan>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "bank_loan")]
+pub struct BankLoan {
+    #[sii(id)]
+    pub id: ID,
+    pub amount: i64,
+    pub original_amount: i64,
+    /// Length of the loan, in monthly payments.
+    pub duration: u32,
+    #[sii(optional)]
+    pub interest_rate: Option<f32>,
+    #[sii(optional)]
+    pub time_stamp: Option<u32>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "garage")]
+pub struct Garage {
+    #[sii(id)]
+    pub id: ID,
+    /// City token, taken from the garage ID (`garage.<city>`).
+    #[sii(with = "garage_city")]
+    pub city: String,
+    /// 0 if the garage has not been bought; otherwise its size.
+    pub status: u32,
+    /// Vehicle slots; empty slots are `None`.
+    pub vehicles: Vec<Option<ID>>,
+    /// Driver slots; empty slots are `None`.
+    pub drivers: Vec<Option<ID>>,
+    #[sii(default)]
+    pub trailers: Vec<Option<ID>>,
+    pub profit_log: Option<ID>,
+}
+
+impl Garage {
+    pub fn is_owned(&self) -> bool {
+        self.status != 0
+    }
+}
+
+fn garage_city(value: &Struct) -> Result<String> {
+    value
+        .id
+        .string_part(-1)
+        .ok_or_else(|| anyhow!("garage {:?} is not named after a city", value.id))
+}
+
+/// Every garage listed in the economy, owned or not.
+impl FromGameSave for Vec<Garage> {
+    fn from_game_save(save: &GameSave) -> Result<Self> {
+        Economy::from_game_save(save)?
+            .garages
+            .iter()
+            .map(|id| resolve_ref(save, id))
+            .collect()
+    }
+}
+
+/// The player's own driver, as opposed to hired `driver_ai`s.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "driver_player", singleton)]
+pub struct DriverPlayer {
+    #[sii(id)]
+    pub id: ID,
+    pub profit_log: Option<ID>,
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        sii::{
+            game::{Bank, BankLoan, Economy, FromGameSave, GameSave, Garage, Player, SaveSummary},
+            value::{EncodedString, Value, ID},
+        },
+        test_util::{block, token},
+    };
+
+    fn token_value(s: &str) -> EncodedString {
+        EncodedString::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn resolves_singletons_through_economy() -> Result<()> {
+        let garage = ID::try_from("garage.berlin")?;
+        let save: GameSave = [
+            block(
+                ID::Nameless(1),
+                "economy",
+                vec![
+                    ("bank", Value::ID(ID::Nameless(2))),
+                    ("player", Value::ID(ID::Nameless(3))),
+                    ("companies", Value::IDArray(vec![])),
+                    ("garages", Value::IDArray(vec![garage.clone()])),
+                    ("game_time", Value::UInt32(4000)),
+                    ("experience_points", Value::UInt32(1234)),
+                    ("adr", Value::UInt32(0b101)),
+                    ("long_dist", Value::UInt32(1)),
+                    ("heavy", Value::UInt32(2)),
+                    ("fragile", Value::UInt32(3)),
+                    ("urgent", Value::UInt32(4)),
+                    ("mechanical", Value::UInt32(5)),
+                    ("total_distance", Value::UInt32(999)),
+                    ("visited_cities", Value::EncodedStringArray(vec![token_value("berlin")])),
+                    ("visited_cities_count", Value::UInt32Array(vec![])),
+                    ("total_fuel_litres", Value::UInt32(10)),
+                    ("total_fuel_price", Value::Int64(20)),
+                    ("gas_station_visit_count", Value::UInt32(1)),
+                ],
+            ),
+            block(
+                ID::Nameless(2),
+                "bank",
+                vec![
+                    ("money_account", Value::Int64(-500)),
+                    ("loan_limit", Value::UInt32(2)),
+                    ("overdraft", Value::ByteBool(true)),
+                    ("loans", Value::IDArray(vec![ID::Nameless(4)])),
+                ],
+            ),
+            block(
+                ID::Nameless(4),
+                "bank_loan",
+                vec![
+                    ("amount", Value::UInt32(9000)),
+                    ("original_amount", Value::UInt32(10000)),
+                    ("duration", Value::UInt32(12)),
+                ],
+            ),
+            block(
+                ID::Nameless(3),
+                "player",
+                vec![
+                    ("hq_city", token("berlin")),
+                    ("assigned_truck", Value::ID(ID::Nameless(5))),
+                    ("assigned_trailer", Value::ID(ID::Named(vec![]))),
+                    ("current_job", Value::ID(ID::Named(vec![]))),
+                    ("trucks", Value::IDArray(vec![ID::Nameless(5)])),
+                    ("trailers", Value::IDArray(vec![])),
+                    ("drivers", Value::IDArray(vec![ID::Nameless(6)])),
+                ],
+            ),
+            block(
+                garage,
+                "garage",
+                vec![
+                    ("status", Value::UInt32(3)),
+                    ("vehicles", Value::IDArray(vec![ID::Nameless(5), ID::Named(vec![])])),
+                    ("drivers", Value::IDArray(vec![ID::Named(vec![]), ID::Named(vec![])])),
+                    ("profit_log", Value::ID(ID::Nameless(7))),
+                ],
+            ),
+            block(
+                ID::Nameless(8),
+                "delivery_log",
+                vec![("entries", Value::IDArray(vec![ID::Nameless(9), ID::Nameless(10)]))],
+            ),
+        ]
+        .into_iter()
+        .collect();
+
+        let player = Player::from_game_save(&save)?;
+        assert_eq!(player.hq_city, "berlin");
+        assert_eq!(player.assigned_truck, Some(ID::Nameless(5)));
+        assert_eq!(player.assigned_trailer, None);
+
+        let bank = Bank::from_game_save(&save)?;
+        assert_eq!(bank.money_account, -500);
+        assert_eq!(bank.loans.len(), 1);
+        assert_eq!(bank.loans[0].amount, 9000);
+        assert_eq!(bank.loans[0].interest_rate, None);
+
+        let garages = Vec::<Garage>::from_game_save(&save)?;
+        assert_eq!(garages[0].city, "berlin");
+        assert!(garages[0].is_owned());
+        assert_eq!(garages[0].vehicles, vec![Some(ID::Nameless(5)), None]);
+        assert!(garages[0].trailers.is_empty());
+
+        let econ = Economy::from_game_save(&save)?;
+        assert_eq!(econ.skills.long_distance, 1);
+        assert_eq!(econ.skills.adr, 0b101);
+
+        let summary = SaveSummary::from_game_save(&save)?;
+        assert_eq!(summary.total_fuel_liters, 10);
+        assert_eq!(summary.total_distance_driven, 999);
+        assert_eq!(summary.total_cities_visited, 1);
+        assert_eq!(summary.total_deliveries, 2);
+
+        let loan_block = save.get_block_by_id(&ID::Nameless(4)).unwrap();
+        assert!(BankLoan::try_from(loan_block).is_ok());
+        let player_block = save.get_block_by_id(&ID::Nameless(3)).unwrap();
+        assert!(BankLoan::try_from(player_block).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use std::{collections::HashMap, io::Write, mem};
+
+use anyhow::{anyhow, bail, Result};
+use flate2::{write::ZlibEncoder, Compression};
+
+use crate::{
+    crypt::sii::Encryptor,
+    sii::{
+        binary::{Schema, Writer},
+        value::{Struct, Value, ID},
+    },
+};
+
+use super::{refs, GameSave};
+
+/// Array fields whose positions are meaningful (garage slots), so deleted
+/// entries are nulled rather than removed.
+const SLOT_ARRAYS: &[(&str, &str)] = &[
+    ("garage", "vehicles"),
+    ("garage", "drivers"),
+    ("garage", "trailers"),
+];
+
+fn check_ordinal(schema: Option<&Schema>, field: &str, value: &Value) -> Result<()> {
+    let (Some(schema), Value::OrdinalString(s)) = (schema, value) else {
+        return Ok(());
+    };
+
+    let table = schema
+        .fields
+        .iter()
+        .find(|f| f.name == field)
+        .and_then(|f| f.ordinal_table.as_ref());
+    match table {
+        Some(t) if t.ordinal_of(s).is_some() => Ok(()),
+        _ => bail!("'{s}' is not a valid value for {}.{field}", schema.name),
+    }
+}
+
+impl GameSave {
+    /// Replaces the value of an existing field, returning the old value.  The
+    /// new value must have the same type as the old one.
+    pub fn set_field(&mut self, id: &ID, field: &str, value: Value) -> Result<Value> {
+        let block = self
+            .blocks
+            .get_mut(id)
+            .ok_or_else(|| anyhow!("no block {id:?}"))?;
+        let old = block
+            .fields
+            .get(field)
+            .ok_or_else(|| anyhow!("missing field {field} in {} {id:?}", block.struct_name))?;
+        if mem::discriminant(old) != mem::discriminant(&value) {
+            bail!(
+                "cannot set {field} in {} {id:?}: expected {} but got {}",
+                block.struct_name,
+                old.type_name(),
+                value.type_name()
+            );
+        }
+        check_ordinal(self.schemas.get(&block.struct_name), field, &value)?;
+
+        refs::unindex_block(&mut self.referrers, block);
+        let old = block.fields.insert(field.to_owned(), value).expect("field exists");
+        refs::index_block(&mut self.referrers, block);
+        Ok(old)
+    }
+
+    /// Adds a block at the end of the save with a fresh nameless ID.  `fields`
+    /// must match the save's schema for `struct_name`, so the save must already
+    /// have a block of that kind.
+    pub fn insert_block(
+        &mut self,
+        struct_name: &str,
+        fields: HashMap<String, Value>,
+    ) -> Result<ID> {
+        let schema = self
+            .schemas
+            .get(struct_name)
+            .ok_or_else(|| anyhow!("no schema for {struct_name} in this save"))?;
+        for field in &schema.fields {
+            match fields.get(&field.name) {
+                None => bail!("missing field {} for {struct_name}", field.name),
+                Some(v) if !v.matches_type(field.value_type) => bail!(
+                    "mismatched type for {} in {struct_name}: got {}",
+                    field.name,
+                    v.type_name()
+                ),
+                Some(v) => check_ordinal(Some(schema), &field.name, v)?,
+            }
+        }
+        if fields.len() != schema.fields.len() {
+            bail!("unexpected fields for {struct_name}");
+        }
+
+        let next = self
+            .blocks
+            .keys()
+            .filter_map(|id| match id {
+                ID::Nameless(n) => Some(*n),
+                ID::Named(_) => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("out of nameless IDs"))?;
+        let id = ID::Nameless(next);
+        let block = Struct {
+            id: id.clone(),
+            struct_name: struct_name.to_owned(),
+            fields,
+        };
+
+        refs::index_block(&mut self.referrers, &block);
+        self.order.push(id.clone());
+        self.blocks.insert(id.clone(), block);
+        Ok(id)
+    }
+
+    /// Removes a block and every reference to it: single references become
+    /// null, and the block is dropped from arrays (or nulled, for garage
+    /// slots).  Blocks that only the deleted block referred to are kept.
+    pub fn delete_block(&mut self, id: &ID) -> Result<Struct> {
+        let removed = self
+            .blocks
+            .remove(id)
+            .ok_or_else(|| anyhow!("no block {id:?}"))?;
+        self.order.retain(|b| b != id);
+        refs::unindex_block(&mut self.referrers, &removed);
+
+        let mut referring: Vec<ID> = self
+            .references_to(id)
+            .iter()
+            .map(|r| r.from.clone())
+            .collect();
+        referring.sort();
+        referring.dedup();
+
+        for from in referring {
+            let Some(block) = self.blocks.get_mut(&from) else {
+                continue;
+            };
+
+            refs::unindex_block(&mut self.referrers, block);
+            for (field, value) in block.fields.iter_mut() {
+                match value {
+                    Value::ID(x) if x == id => *x = ID::Named(vec![]),
+                    Value::IDArray(ids) => {
+                        if SLOT_ARRAYS.contains(&(block.struct_name.as_str(), field.as_str())) {
+                            for x in ids.iter_mut().filter(|x| *x == id) {
+                                *x = ID::Named(vec![]);
+                            }
+                        } else {
+                            ids.retain(|x| x != id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            refs::index_block(&mut self.referrers, block);
+        }
+
+        Ok(removed)
+    }
+
+    /// Writes the save as a binary sii file, which the game loads as-is.  This
+    /// is the way to write edited saves.
+    pub fn write_binary<W: Write>(&self, writer: W) -> Result<()> {
+        let mut out = Writer::new(writer, self.version)?;
+        for id in &self.order {
+            let block = &self.blocks[id];
+            let schema = self
+                .schemas
+                .get(&block.struct_name)
+                .ok_or_else(|| anyhow!("no schema for {}", block.struct_name))?;
+            out.write_struct(schema, block)?;
+        }
+
+        out.finish()?;
+        Ok(())
+    }
+
+    /// Writes the save compressed and encrypted, in the game's own format.
+    ///
+    /// Experimental: the header's HMAC is left zeroed (see
+    /// [`Encryptor::encrypt`]) and the game has not been checked to load the
+    /// result.  Prefer [`write_binary`](Self::write_binary).
+    pub fn write_encrypted<W: Write>(&self, writer: W) -> Result<()> {
+        let mut plain = Vec::new();
+        self.write_binary(&mut plain)?;
+        let plain_len = u32::try_from(plain.len()).map_err(|_| anyhow!("save is too large"))?;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&plain)?;
+        Encryptor::new(writer).encrypt(&encoder.finish()?, plain_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+    use flate2::read::ZlibDecoder;
+
+    use crate::{
+        crypt::sii::Decryptor,
+        sii::{
+            binary::{Schema, StructFieldDef, Writer},
+            game::GameSave,
+            value::{Value, ID},
+        },
+        test_util::block,
+    };
+
+    fn schema(id: u32, name: &str, fields: &[(u32, &str)]) -> Schema {
+        Schema {
+            id,
+            name: name.to_owned(),
+            fields: fields
+                .iter()
+                .map(|(value_type, name)| StructFieldDef {
+                    value_type: *value_type,
+                    name: name.to_string(),
+                    ordinal_table: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn test_save() -> Result<GameSave> {
+        let player = schema(1, "player", &[(0x39, "assigned_truck"), (0x3A, "trucks")]);
+        let vehicle = schema(2, "vehicle", &[(0x05, "engine_wear"), (0x01, "license_plate")]);
+        let garage = schema(3, "garage", &[(0x3A, "vehicles"), (0x27, "status")]);
+
+        let mut writer = Writer::new(Vec::new(), 2)?;
+        writer.write_struct(
+            &player,
+            &block(
+                ID::Nameless(1),
+                "player",
+                vec![
+                    ("assigned_truck", Value::ID(ID::Nameless(2))),
+                    ("trucks", Value::IDArray(vec![ID::Nameless(2), ID::Nameless(3)])),
+                ],
+            ),
+        )?;
+        for (id, wear) in [(2, 0.5), (3, 0.0)] {
+            writer.write_struct(
+                &vehicle,
+                &block(
+                    ID::Nameless(id),
+                    "vehicle",
+                    vec![
+                        ("engine_wear", Value::Single(wear)),
+                        ("license_plate", Value::String(format!("TEST {id}"))),
+                    ],
+                ),
+            )?;
+        }
+        writer.write_struct(
+            &garage,
+            &block(
+                ID::try_from("garage.berlin")?,
+                "garage",
+                vec![
+                    ("vehicles", Value::IDArray(vec![ID::Nameless(3), ID::Named(vec![])])),
+                    ("status", Value::UInt32(2)),
+                ],
+            ),
+        )?;
+
+        GameSave::new(writer.finish()?.as_slice())
+    }
+
+    #[test]
+    fn binary_round_trip() -> Result<()> {
+        let save = test_save()?;
+        let mut out = Vec::new();
+        save.write_binary(&mut out)?;
+
+        let reread = GameSave::new(out.as_slice())?;
+        for (id, block) in save.iter_blocks() {
+            assert_eq!(reread.get_block_by_id(id), Some(block));
+        }
+        let mut again = Vec::new();
+        reread.write_binary(&mut again)?;
+        assert_eq!(out, again);
+        Ok(())
+    }
+
+    #[test]
+    fn edits_are_type_checked_and_written() -> Result<()> {
+        let mut save = test_save()?;
+        let truck = ID::Nameless(2);
+        assert!(save.set_field(&truck, "engine_wear", Value::UInt32(0)).is_err());
+        assert!(save.set_field(&truck, "no_such_field", Value::Single(0.0)).is_err());
+        assert_eq!(save.set_field(&truck, "engine_wear", Value::Single(0.0))?, Value::Single(0.5));
+
+        let new_truck = save.insert_block(
+            "vehicle",
+            HashMap::from([
+                ("engine_wear".to_owned(), Value::Single(0.0)),
+                ("license_plate".to_owned(), Value::String("NEW".to_owned())),
+            ]),
+        )?;
+        assert_eq!(new_truck, ID::Nameless(4));
+        assert!(save.insert_block("vehicle", HashMap::new()).is_err());
+        assert!(save.insert_block("bank", HashMap::new()).is_err());
+        let player = ID::Nameless(1);
+        save.set_field(&player, "assigned_truck", Value::ID(new_truck.clone()))?;
+        assert_eq!(save.references_to(&new_truck).len(), 1);
+        assert_eq!(save.references_to(&truck).len(), 1);
+
+        save.delete_block(&ID::Nameless(3))?;
+        let garage = save.get_block_by_id(&ID::try_from("garage.berlin")?).unwrap();
+        assert_eq!(garage.get_as::<Vec<Option<ID>>>("vehicles")?, vec![None, None]);
+        let p = save.get_block_by_id(&player).unwrap();
+        assert_eq!(p.get_as::<Vec<ID>>("trucks")?, vec![truck.clone()]);
+        assert!(save.dangling_references().is_empty());
+
+        let mut out = Vec::new();
+        save.write_encrypted(&mut out)?;
+        let decrypted = Decryptor::new(out.as_slice()).decrypt()?;
+        let reread = GameSave::new(ZlibDecoder::new(decrypted.as_slice()))?;
+        let t = reread.get_block_by_id(&truck).unwrap();
+        assert_eq!(t.get_as::<f32>("engine_wear")?, 0.0);
+        let p = reread.get_block_by_id(&player).unwrap();
+        assert_eq!(reread.resolve(p, "assigned_truck")?.unwrap().id, new_truck);
+        assert!(reread.get_block_by_id(&ID::Nameless(3)).is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,448 @@
+use anyhow::Result;
+
+use crate::sii::value::{Struct, ID};
+
+use super::{resolve_ref, FromGameSave, FromStruct, GameSave, Garage, Player, Skills};
+
+/// Any accessory attached to a truck or trailer: the chassis, engine, paint
+/// job, addons and so on.  They all have a `data_path` into the def files,
+/// but come in several struct types (`vehicle_paint_job_accessory`, ...).
+#[derive(Debug, Clone, FromStruct)]
+pub struct VehicleAccessory {
+    #[sii(id)]
+    pub id: ID,
+    #[sii(with = "struct_name")]
+    pub kind: String,
+    pub data_path: String,
+}
+
+fn struct_name(value: &Struct) -> Result<String> {
+    Ok(value.struct_name.clone())
+}
+
+/// Brand and model tokens from the truck's chassis accessory, whose data path
+/// is `/def/vehicle/truck/<brand>.<model>/data.sii`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TruckModel {
+    pub brand: String,
+    pub model: String,
+}
+
+impl TruckModel {
+    pub fn from_data_path(path: &str) -> Option<Self> {
+        let rest = path.strip_prefix("/def/vehicle/truck/")?;
+        let (dir, file) = rest.split_once('/')?;
+        if file != "data.sii" {
+            return None;
+        }
+
+        let (brand, model) = dir.split_once('.')?;
+        Some(Self {
+            brand: brand.to_owned(),
+            model: model.to_owned(),
+        })
+    }
+}
+
+/// Wear is 0.0 (new) to 1.0 (broken).
+#[derive(Debug, Clone, Default, PartialEq, FromStruct)]
+pub struct TruckWear {
+    #[sii(rename = "engine_wear", default)]
+    pub engine: f32,
+    #[sii(rename = "transmission_wear", default)]
+    pub transmission: f32,
+    #[sii(rename = "cabin_wear", default)]
+    pub cabin: f32,
+    #[sii(rename = "chassis_wear", default)]
+    pub chassis: f32,
+    /// One per wheel.
+    #[sii(rename = "wheels_wear", default)]
+    pub wheels: Vec<f32>,
+}
+
+/// A truck, which the save calls a `vehicle`.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "vehicle")]
+pub struct Truck {
+    #[sii(id)]
+    pub id: ID,
+    pub license_plate: String,
+    #[sii(resolve)]
+    pub accessories: Vec<VehicleAccessory>,
+    #[sii(with_save = "truck_model")]
+    pub model: Option<TruckModel>,
+    /// Whole km.
+    pub odometer: u32,
+    #[sii(default)]
+    pub odometer_float_part: f32,
+    #[sii(flatten)]
+    pub wear: TruckWear,
+    /// Fuel level, 0.0 - 1.0 of the tank.
+    pub fuel_relative: f32,
+    /// The hired driver assigned to the truck, if any.
+    #[sii(with_save = "assigned_driver")]
+    pub driver: Option<ID>,
+    #[sii(with_save = "owning_garage")]
+    pub garage: Option<ID>,
+}
+
+impl Truck {
+    pub fn odometer_km(&self) -> f64 {
+        self.odometer as f64 + self.odometer_float_part as f64
+    }
+}
+
+fn truck_model(value: &Struct, save: &GameSave) -> Result<Option<TruckModel>> {
+    Ok(save
+        .resolve_array(value, "accessories")?
+        .iter()
+        .filter_map(|a| a.get_as::<String>("data_path").ok())
+        .find_map(|p| TruckModel::from_data_path(&p)))
+}
+
+/// The first block of type `struct_name` whose `field` refers to `value`.
+fn referrer(value: &Struct, save: &GameSave, struct_name: &str, field: &str) -> Option<ID> {
+    save.references_to(&value.id)
+        .iter()
+        .filter(|r| r.field == field)
+        .find(|r| {
+            save.get_block_by_id(&r.from)
+                .is_some_and(|b| b.struct_name == struct_name)
+        })
+        .map(|r| r.from.clone())
+}
+
+fn assigned_driver(value: &Struct, save: &GameSave) -> Result<Option<ID>> {
+    if let Some(driver) = referrer(value, save, "driver_ai", "assigned_truck") {
+        return Ok(Some(driver));
+    }
+
+    // Older saves only pair them up by garage slot.
+    let slot = save.references_to(&value.id).iter().find(|r| {
+        r.field == "vehicles"
+            && save.get_block_by_id(&r.from).is_some_and(|b| b.struct_name == "garage")
+    });
+    let Some(slot) = slot else {
+        return Ok(None);
+    };
+    let garage = save.get_block_by_id(&slot.from).expect("referrer exists");
+    let drivers = garage.get_as::<Vec<Option<ID>>>("drivers")?;
+    Ok(slot.index.and_then(|i| drivers.get(i).cloned().flatten()))
+}
+
+fn owning_garage(value: &Struct, save: &GameSave) -> Result<Option<ID>> {
+    let field = if value.struct_name == "driver_ai" {
+        "drivers"
+    } else if value.struct_name == "trailer" {
+        "trailers"
+    } else {
+        "vehicles"
+    };
+    Ok(referrer(value, save, "garage", field))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, FromStruct)]
+pub struct TrailerWear {
+    #[sii(rename = "trailer_body_wear", default)]
+    pub body: f32,
+    #[sii(rename = "chassis_wear", default)]
+    pub chassis: f32,
+    #[sii(rename = "wheels_wear", default)]
+    pub wheels: Vec<f32>,
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "trailer")]
+pub struct Trailer {
+    #[sii(id)]
+    pub id: ID,
+    /// Points into the def files (`trailer_def.*`), not at another block.
+    pub trailer_definition: ID,
+    pub license_plate: String,
+    #[sii(resolve)]
+    pub accessories: Vec<VehicleAccessory>,
+    pub odometer: u32,
+    #[sii(flatten)]
+    pub wear: TrailerWear,
+    pub cargo_mass: f32,
+    pub cargo_damage: f32,
+    /// The next trailer of a double or B-double.
+    #[sii(default)]
+    pub slave_trailer: Option<ID>,
+    #[sii(with_save = "owning_garage")]
+    pub garage: Option<ID>,
+}
+
+/// Totals over a driver's `profit_log`, which keeps a few weeks of history.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Earnings {
+    /// In the game's currency: euros in ETS2, dollars in ATS.
+    pub revenue: i64,
+    pub wage: i64,
+    pub maintenance: i64,
+    pub fuel: i64,
+    /// In km for both games.
+    pub distance: u64,
+}
+
+impl Earnings {
+    /// What the driver made for the company after their own costs.
+    pub fn profit(&self) -> i64 {
+        self.revenue - self.wage - self.maintenance - self.fuel
+    }
+}
+
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "profit_log_entry")]
+struct ProfitLogEntry {
+    #[sii(default)]
+    revenue: i64,
+    #[sii(default)]
+    wage: i64,
+    #[sii(default)]
+    maintenance: i64,
+    #[sii(default)]
+    fuel: i64,
+    #[sii(default)]
+    distance: u64,
+}
+
+fn driver_earnings(value: &Struct, save: &GameSave) -> Result<Earnings> {
+    let Some(log) = save.resolve(value, "profit_log")? else {
+        return Ok(Earnings::default());
+    };
+
+    let mut total = Earnings::default();
+    for id in log.get_as::<Vec<ID>>("stats_data")? {
+        let e: ProfitLogEntry = resolve_ref(save, &id)?;
+        total.revenue += e.revenue;
+        total.wage += e.wage;
+        total.maintenance += e.maintenance;
+        total.fuel += e.fuel;
+        total.distance += e.distance;
+    }
+
+    Ok(total)
+}
+
+/// A hired driver.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "driver_ai")]
+pub struct DriverAi {
+    #[sii(id)]
+    pub id: ID,
+    #[sii(flatten)]
+    pub skills: Skills,
+    pub experience_points: u32,
+    pub hometown: String,
+    pub current_city: String,
+    #[sii(default)]
+    pub assigned_truck: Option<ID>,
+    #[sii(default)]
+    pub assigned_trailer: Option<ID>,
+    #[sii(with_save = "driver_earnings")]
+    pub earnings: Earnings,
+    #[sii(with_save = "owning_garage")]
+    pub garage: Option<ID>,
+}
+
+/// Everything the player's company owns or employs.
+#[derive(Debug, Clone)]
+pub struct Fleet {
+    pub trucks: Vec<Truck>,
+    pub trailers: Vec<Trailer>,
+    pub drivers: Vec<DriverAi>,
+    /// Owned garages only, by city.
+    pub garages: Vec<Garage>,
+}
+
+fn resolve_all<T: FromStruct>(save: &GameSave, ids: &[ID]) -> Result<Vec<T>> {
+    ids.iter().map(|id| resolve_ref(save, id)).collect()
+}
+
+impl FromGameSave for Fleet {
+    fn from_game_save(save: &GameSave) -> Result<Self> {
+        let player = Player::from_game_save(save)?;
+        // player.drivers starts with the player's own driver_player.
+        let hired: Vec<ID> = player
+            .drivers
+            .into_iter()
+            .filter(|id| save.get_block_by_id(id).is_some_and(|b| b.struct_name == "driver_ai"))
+            .collect();
+
+        let mut garages = save
+            .iter_blocks_named("garage")
+            .map(|(_, b)| Garage::from_struct(b, Some(save)))
+            .filter(|g| g.as_ref().map_or(true, Garage::is_owned))
+            .collect::<Result<Vec<_>>>()?;
+        garages.sort_by(|a, b| a.city.cmp(&b.city));
+
+        Ok(Self {
+            trucks: resolve_all(save, &player.trucks)?,
+            trailers: resolve_all(save, &player.trailers)?,
+            drivers: resolve_all(save, &hired)?,
+            garages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        sii::{
+            game::{FromGameSave, GameSave},
+            value::{Value, ID},
+        },
+        test_util::{block, token},
+    };
+
+    use super::{Fleet, TruckModel};
+
+    fn ids(ids: &[u64]) -> Value {
+        Value::IDArray(ids.iter().map(|i| ID::Nameless(*i)).collect())
+    }
+
+    fn fleet_save(with_assignment: bool) -> Result<GameSave> {
+        let garage = ID::try_from("garage.berlin")?;
+        let null = ID::Named(vec![]);
+        let mut driver_fields = vec![
+            ("adr", Value::UInt32(1)),
+            ("long_dist", Value::UInt32(2)),
+            ("heavy", Value::UInt32(3)),
+            ("fragile", Value::UInt32(4)),
+            ("urgent", Value::UInt32(5)),
+            ("mechanical", Value::UInt32(6)),
+            ("experience_points", Value::UInt32(700)),
+            ("hometown", token("berlin")),
+            ("current_city", token("dresden")),
+            ("profit_log", Value::ID(ID::Nameless(30))),
+        ];
+        if with_assignment {
+            driver_fields.push(("assigned_truck", Value::ID(ID::Nameless(10))));
+        }
+
+        Ok([
+            block(ID::Nameless(1), "economy", vec![("garages", Value::IDArray(vec![garage.clone()]))]),
+            block(
+                ID::Nameless(2),
+                "player",
+                vec![
+                    ("hq_city", token("berlin")),
+                    ("assigned_truck", Value::ID(null.clone())),
+                    ("assigned_trailer", Value::ID(null.clone())),
+                    ("current_job", Value::ID(null.clone())),
+                    ("trucks", ids(&[10])),
+                    ("trailers", ids(&[40])),
+                    ("drivers", ids(&[3, 20])),
+                ],
+            ),
+            block(ID::Nameless(3), "driver_player", vec![]),
+            block(
+                ID::Nameless(10),
+                "vehicle",
+                vec![
+                    ("license_plate", Value::String("B AB 123".to_owned())),
+                    ("accessories", ids(&[11, 12])),
+                    ("odometer", Value::UInt32(120000)),
+                    ("odometer_float_part", Value::Single(0.5)),
+                    ("engine_wear", Value::Single(0.1)),
+                    ("wheels_wear", Value::SingleArray(vec![0.2, 0.3])),
+                    ("fuel_relative", Value::Single(0.75)),
+                ],
+            ),
+            block(
+                ID::Nameless(11),
+                "vehicle_paint_job_accessory",
+                vec![("data_path", Value::String("/def/vehicle/truck/scania.r/paint_job/default.sii".to_owned()))],
+            ),
+            block(
+                ID::Nameless(12),
+                "vehicle_accessory",
+                vec![("data_path", Value::String("/def/vehicle/truck/scania.r/data.sii".to_owned()))],
+            ),
+            block(ID::Nameless(20), "driver_ai", driver_fields),
+            block(ID::Nameless(30), "profit_log", vec![("stats_data", ids(&[31, 32]))]),
+            block(
+                ID::Nameless(31),
+                "profit_log_entry",
+                vec![("revenue", Value::Int64(5000)), ("wage", Value::Int64(1000)), ("distance", Value::UInt32(600))],
+            ),
+            block(
+                ID::Nameless(32),
+                "profit_log_entry",
+                vec![("revenue", Value::Int64(3000)), ("fuel", Value::Int64(500)), ("distance", Value::UInt32(400))],
+            ),
+            block(
+                ID::Nameless(40),
+                "trailer",
+                vec![
+                    ("trailer_definition", Value::ID(ID::try_from("trailer_def.scs.box")?)),
+                    ("license_plate", Value::String("B CD 456".to_owned())),
+                    ("accessories", ids(&[])),
+                    ("odometer", Value::UInt32(5000)),
+                    ("trailer_body_wear", Value::Single(0.4)),
+                    ("cargo_mass", Value::Single(0.0)),
+                    ("cargo_damage", Value::Single(0.0)),
+                ],
+            ),
+            block(
+                garage,
+                "garage",
+                vec![
+                    ("status", Value::UInt32(2)),
+                    ("vehicles", Value::IDArray(vec![null.clone(), ID::Nameless(10)])),
+                    ("drivers", Value::IDArray(vec![null, ID::Nameless(20)])),
+                    ("trailers", ids(&[40])),
+                    ("profit_log", Value::ID(ID::Nameless(30))),
+                ],
+            ),
+        ]
+        .into_iter()
+        .collect())
+    }
+
+    #[test]
+    fn builds_fleet_with_references() -> Result<()> {
+        let garage = ID::try_from("garage.berlin")?;
+        for with_assignment in [true, false] {
+            let fleet = Fleet::from_game_save(&fleet_save(with_assignment)?)?;
+
+            let truck = &fleet.trucks[0];
+            assert_eq!(
+                truck.model,
+                Some(TruckModel { brand: "scania".to_owned(), model: "r".to_owned() })
+            );
+            assert_eq!(truck.odometer_km(), 120000.5);
+            assert_eq!(truck.wear.wheels, vec![0.2, 0.3]);
+            assert_eq!(truck.wear.cabin, 0.0);
+            assert_eq!(truck.driver, Some(ID::Nameless(20)));
+            assert_eq!(truck.garage.as_ref(), Some(&garage));
+
+            assert_eq!(fleet.drivers.len(), 1);
+            let driver = &fleet.drivers[0];
+            assert_eq!(driver.skills.mechanical, 6);
+            assert_eq!(driver.earnings.revenue, 8000);
+            assert_eq!(driver.earnings.distance, 1000);
+            assert_eq!(driver.earnings.profit(), 6500);
+            assert_eq!(driver.garage.as_ref(), Some(&garage));
+
+            assert_eq!(fleet.trailers[0].wear.body, 0.4);
+            assert_eq!(fleet.trailers[0].garage.as_ref(), Some(&garage));
+            assert_eq!(fleet.garages.len(), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_truck_data_paths() {
+        assert_eq!(
+            TruckModel::from_data_path("/def/vehicle/truck/mercedes.actros2014/data.sii"),
+            Some(TruckModel { brand: "mercedes".to_owned(), model: "actros2014".to_owned() })
+        );
+        assert_eq!(TruckModel::from_data_path("/def/vehicle/truck/daf.xf/engine/mx13.sii"), None);
+        assert_eq!(TruckModel::from_data_path("/def/vehicle/trailer/krone/data.sii"), None);
+    }
+}
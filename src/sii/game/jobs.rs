@@ -0,0 +1,178 @@
+use anyhow::{anyhow, Result};
+
+use crate::sii::value::{Struct, ID};
+
+use super::{FromGameSave, FromStruct, GameSave};
+
+/// Prefix of the company depot IDs in saves, followed by `<company>.<city>`.
+/// ETS2 and ATS saves both use it.
+pub const COMPANY_PREFIX: &str = "company.volatile.";
+
+/// One slot of a company's job board.  Slots without a job have no target or
+/// cargo.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "job_offer_data")]
+pub struct JobOffer {
+    #[sii(id)]
+    pub id: ID,
+    /// `company.volatile.<company>.<city>`, like delivery log entries.
+    #[sii(with = "offer_target")]
+    pub target: Option<ID>,
+    pub cargo: Option<ID>,
+    #[sii(default)]
+    pub urgency: u32,
+    #[sii(rename = "shortest_distance_km", default)]
+    pub distance_km: u32,
+    /// In-game minutes since the profile was started, like `Economy::game_time`.
+    #[sii(default)]
+    pub expiration_time: u32,
+    #[sii(default)]
+    pub units_count: u32,
+}
+
+fn offer_target(value: &Struct) -> Result<Option<ID>> {
+    let target: String = value.get_as("target")?;
+    if target.is_empty() {
+        return Ok(None);
+    }
+
+    ID::try_from(format!("{COMPANY_PREFIX}{target}")).map(Some)
+}
+
+impl JobOffer {
+    pub fn is_empty(&self) -> bool {
+        self.target.is_none() || self.cargo.is_none()
+    }
+
+    pub fn target_city(&self) -> Option<String> {
+        self.target.as_ref()?.string_part(-1)
+    }
+
+    pub fn target_company(&self) -> Option<String> {
+        self.target.as_ref()?.string_part(-2)
+    }
+
+    /// Cargo token, without the `cargo.` prefix.
+    pub fn cargo_name(&self) -> Option<String> {
+        self.cargo.as_ref()?.string_part(-1)
+    }
+}
+
+/// A company depot (`company.volatile.<company>.<city>`) and its job board.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "company")]
+pub struct Company {
+    #[sii(id)]
+    pub id: ID,
+    #[sii(rename = "job_offer", resolve)]
+    pub job_offers: Vec<JobOffer>,
+    #[sii(default)]
+    pub discovered: bool,
+}
+
+impl Company {
+    pub fn city(&self) -> Option<String> {
+        self.id.string_part(-1)
+    }
+
+    /// Company token, without the city.
+    pub fn company_name(&self) -> Option<String> {
+        self.id.string_part(-2)
+    }
+}
+
+/// Every company's job offers at the time of the save.
+#[derive(Debug, Clone)]
+pub struct JobBoard {
+    pub game_time: u32,
+    pub companies: Vec<Company>,
+}
+
+impl JobBoard {
+    /// Offers that can be taken right now, with the company offering them.
+    pub fn open_jobs(&self) -> impl Iterator<Item = (&Company, &JobOffer)> {
+        self.companies.iter().flat_map(move |c| {
+            c.job_offers
+                .iter()
+                .filter(move |o| !o.is_empty() && o.expiration_time > self.game_time)
+                .map(move |o| (c, o))
+        })
+    }
+}
+
+impl FromGameSave for JobBoard {
+    fn from_game_save(save: &GameSave) -> Result<Self> {
+        let game_time = save
+            .single_block_named("economy")
+            .ok_or_else(|| anyhow!("missing economy data"))?
+            .get_as("game_time")?;
+        let mut companies = save
+            .iter_blocks_named("company")
+            .map(|(_, b)| Company::from_struct(b, Some(save)))
+            .collect::<Result<Vec<_>>>()?;
+        companies.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(Self { game_time, companies })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        sii::{
+            game::{FromGameSave, GameSave},
+            value::{Struct, Value, ID},
+        },
+        test_util::block,
+    };
+
+    use super::JobBoard;
+
+    fn offer(id: u64, target: &str, cargo: Option<&str>, expiration_time: u32) -> Struct {
+        let cargo = cargo.map_or(ID::Named(vec![]), |c| ID::try_from(c).unwrap());
+        block(
+            ID::Nameless(id),
+            "job_offer_data",
+            vec![
+                ("target", Value::String(target.to_owned())),
+                ("cargo", Value::ID(cargo)),
+                ("shortest_distance_km", Value::UInt32(190)),
+                ("expiration_time", Value::UInt32(expiration_time)),
+            ],
+        )
+    }
+
+    #[test]
+    fn lists_open_jobs() -> Result<()> {
+        let save: GameSave = [
+            block(ID::Nameless(1), "economy", vec![("game_time", Value::UInt32(1000))]),
+            block(
+                ID::try_from("company.volatile.tradeaux.berlin")?,
+                "company",
+                vec![(
+                    "job_offer",
+                    Value::IDArray(vec![ID::Nameless(2), ID::Nameless(3), ID::Nameless(4)]),
+                )],
+            ),
+            offer(2, "posped.dresden", Some("cargo.apples"), 2000),
+            offer(3, "", None, 0),
+            offer(4, "posped.dresden", Some("cargo.beef"), 900),
+        ]
+        .into_iter()
+        .collect();
+
+        let board = JobBoard::from_game_save(&save)?;
+        assert_eq!(board.companies[0].job_offers.len(), 3);
+        assert_eq!(board.companies[0].city().as_deref(), Some("berlin"));
+
+        let open: Vec<_> = board.open_jobs().collect();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].1.target_company().as_deref(), Some("posped"));
+        assert_eq!(open[0].1.target_city().as_deref(), Some("dresden"));
+        assert_eq!(open[0].1.cargo_name().as_deref(), Some("apples"));
+        assert_eq!(open[0].1.distance_km, 190);
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::HashMap, io::Read};
+
+use crate::game::Game;
+use anyhow::{anyhow, bail, Result};
+
+use super::{
+    binary::{Block, Parser, Schema},
+    value::{ID, Struct},
+};
+
+pub use siirs_derive::FromStruct;
+
+pub use self::delivery::{DeliveryLogEntry, JobType};
+pub use self::economy::{Bank, BankLoan, DriverPlayer, Economy, Garage, Player, Skills};
+pub use self::fleet::{
+    DriverAi, Earnings, Fleet, Trailer, TrailerWear, Truck, TruckModel, TruckWear,
+    VehicleAccessory,
+};
+pub use self::jobs::{Company, JobBoard, JobOffer, COMPANY_PREFIX};
+pub use self::profile::{
+    load_single, ActiveMod, Dependency, DependencyKind, SaveContainer, UserProfile,
+};
+pub use self::refs::{DanglingReference, Reference};
+
+mod delivery;
+mod economy;
+mod edit;
+mod fleet;
+mod jobs;
+mod profile;
+mod refs;
+
+pub struct GameSave {
+    blocks: HashMap<ID, Struct>,
+    // Block IDs in file order, so the save can be written back unchanged.
+    order: Vec<ID>,
+    // Schemas by struct name; only known for saves read from a file.
+    schemas: HashMap<String, Schema>,
+    version: u32,
+    // Referenced ID -> where it is referenced from.
+    referrers: HashMap<ID, Vec<Reference>>,
+}
+
+pub trait FromGameSave
+where
+    Self: Sized,
+{
+    fn from_game_save(save: &GameSave) -> Result<Self>;
+}
+
+/// Decoding from a single block.  Usually implemented with
+/// `#[derive(FromStruct)]`; see siirs-derive for the attributes it supports.
+pub trait FromStruct
+where
+    Self: Sized,
+{
+    /// If set, blocks with any other struct name are rejected.
+    const STRUCT_NAME: Option<&'static str>;
+
+    /// `save` is needed to follow references to other blocks; types that don't
+    /// do that can be decoded without one.
+    fn from_struct(value: &Struct, save: Option<&GameSave>) -> Result<Self>;
+}
+
+/// Follows a reference to another block and decodes it.
+pub fn resolve_ref<T: FromStruct>(save: &GameSave, id: &ID) -> Result<T> {
+    let block = match T::STRUCT_NAME {
+        Some(name) => save.expect_block(id, name)?,
+        None => save
+            .get_block_by_id(id)
+            .ok_or_else(|| anyhow!("dangling reference to {id:?}"))?,
+    };
+
+    T::from_struct(block, Some(save))
+}
+
+#[doc(hidden)]
+pub mod __derive {
+    pub use anyhow::{anyhow, Error, Result};
+
+    pub use super::{expect_struct_name, resolve_ref, FromGameSave, FromStruct, GameSave};
+    pub use crate::sii::value::{Struct, ID};
+
+    /// Derived for `FromStruct` types that decode without a `GameSave`, so a
+    /// type that flattens one knows whether it does too.
+    pub trait WithoutSave {}
+}
+
+/// Totals over the whole profile.  Distances are in km and money in the
+/// game's currency for both games; see `describe` for display.
+#[derive(Debug, FromStruct)]
+#[sii(struct_name = "economy", singleton)]
+pub struct SaveSummary {
+    #[sii(rename = "total_fuel_litres")]
+    pub total_fuel_liters: u32,
+    #[sii(rename = "total_fuel_price")]
+    pub total_fuel_cost: i64,
+    #[sii(rename = "gas_station_visit_count")]
+    pub total_fuel_visits: u32,
+    #[sii(rename = "experience_points")]
+    pub total_xp: u32,
+    #[sii(rename = "total_distance")]
+    pub total_distance_driven: u32,
+    #[sii(with = "visited_city_count")]
+    pub total_cities_visited: usize,
+    #[sii(with_save = "delivery_count")]
+    pub total_deliveries: usize,
+}
+
+fn visited_city_count(econ: &Struct) -> Result<usize> {
+    Ok(econ.get_as::<Vec<String>>("visited_cities")?.len())
+}
+
+/// The delivery log is its own block rather than part of `economy`.
+fn delivery_count(_econ: &Struct, save: &GameSave) -> Result<usize> {
+    let dlog = save
+        .single_block_named("delivery_log")
+        .ok_or_else(|| anyhow!("missing delivery_log data"))?;
+    Ok(dlog.get_as::<Vec<ID>>("entries")?.len())
+}
+
+impl SaveSummary {
+    /// Labelled values, with distance and money in `game`'s units.
+    pub fn describe(&self, game: Game) -> Vec<(&'static str, String)> {
+        vec![
+            ("Distance driven", game.format_distance(self.total_distance_driven as f64)),
+            ("Deliveries", self.total_deliveries.to_string()),
+            ("Cities visited", self.total_cities_visited.to_string()),
+            ("Experience", self.total_xp.to_string()),
+            ("Fuel", format!("{} l", self.total_fuel_liters)),
+            ("Fuel cost", game.format_money(self.total_fuel_cost)),
+            ("Fuel stops", self.total_fuel_visits.to_string()),
+        ]
+    }
+}
+
+impl GameSave {
+    pub fn new<R: Read>(reader: R) -> Result<Self> {
+        Self::from_parser(Parser::new(reader)?)
+    }
+
+    /// Reads an encrypted save, e.g. `game.sii`.
+    pub fn new_from_save(path: &str) -> Result<Self> {
+        Self::from_parser(Parser::new_from_save(path)?)
+    }
+
+    fn from_parser<R: Read>(mut parser: Parser<R>) -> Result<Self> {
+        let mut objects = Vec::new();
+        let mut schemas = HashMap::new();
+
+        loop {
+            match parser.next_block()? {
+                None => break,
+                Some(Block::Schema(schema)) => {
+                    schemas.insert(schema.name.clone(), schema);
+                }
+                Some(Block::Struct(db)) => objects.push(db),
+            }
+        }
+
+        let mut save = Self::from_blocks(objects);
+        save.schemas = schemas;
+        save.version = parser.version();
+        Ok(save)
+    }
+
+    fn from_blocks(blocks: Vec<Struct>) -> Self {
+        let order = blocks.iter().map(|b| b.id.clone()).collect();
+        let blocks = blocks.into_iter().map(|b| (b.id.clone(), b)).collect();
+        let referrers = refs::index_references(&blocks);
+        Self {
+            blocks,
+            order,
+            schemas: HashMap::new(),
+            version: 2,
+            referrers,
+        }
+    }
+
+    pub fn get_block_by_id(&self, id: &ID) -> Option<&Struct> {
+        self.blocks.get(id)
+    }
+
+    /// Like `get_block_by_id`, but for following a reference that is expected
+    /// to point at a `struct_name` block.
+    fn expect_block(&self, id: &ID, struct_name: &str) -> Result<&Struct> {
+        match self.blocks.get(id) {
+            Some(b) if b.struct_name == struct_name => Ok(b),
+            Some(b) => bail!("expected {id:?} to be a {struct_name} but it is a {}", b.struct_name),
+            None => bail!("dangling reference to {struct_name} {id:?}"),
+        }
+    }
+
+    pub fn iter_blocks_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> Box<dyn Iterator<Item = (&ID, &Struct)> + 'a> {
+        Box::new(
+            self.blocks
+                .iter()
+                .filter(move |(_, s)| s.struct_name == name),
+        )
+    }
+
+    pub fn iter_blocks<'a>(&'a self) -> Box<dyn Iterator<Item = (&ID, &Struct)> + 'a> {
+        Box::new(self.blocks.iter())
+    }
+
+    pub fn single_block_named(&self, name: &str) -> Option<&Struct> {
+        self.blocks
+            .iter()
+            .find(|(_, s)| s.struct_name == name)
+            .map(|(_, block)| block)
+    }
+}
+
+impl FromIterator<Struct> for GameSave {
+    fn from_iter<T: IntoIterator<Item = Struct>>(iter: T) -> Self {
+        Self::from_blocks(iter.into_iter().collect())
+    }
+}
+
+pub fn expect_struct_name(value: &Struct, struct_name: &str) -> Result<()> {
+    if value.struct_name != struct_name {
+        bail!(
+            "expected a {struct_name} block but {:?} is a {}",
+            value.id,
+            value.struct_name
+        );
+    }
+
+    Ok(())
+}
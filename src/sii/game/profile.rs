@@ -0,0 +1,213 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::sii::{format::read_structs_from_path, value::Struct};
+
+use super::FromStruct;
+
+/// `profile.sii`: the profile's appearance and a few cached stats, so the
+/// profile list can be shown without loading a save.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "user_profile")]
+pub struct UserProfile {
+    pub profile_name: String,
+    pub company_name: String,
+    pub face: u32,
+    /// Truck brand shown on the profile card.
+    pub brand: String,
+    pub logo: String,
+    pub male: bool,
+    pub map_path: String,
+    pub cached_experience: u32,
+    pub cached_distance: u32,
+    #[sii(default)]
+    pub cached_discovery: Vec<String>,
+    pub version: u32,
+    /// Unix times.
+    pub creation_time: u64,
+    pub save_time: u64,
+    #[sii(with = "active_mods")]
+    pub active_mods: Vec<ActiveMod>,
+}
+
+/// A mod enabled for the profile, from `"<id>|<display name>"`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ActiveMod {
+    pub id: String,
+    pub name: String,
+}
+
+fn active_mods(value: &Struct) -> Result<Vec<ActiveMod>> {
+    Ok(value
+        .get_opt_as::<Vec<String>>("active_mods")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|m| match m.split_once('|') {
+            Some((id, name)) => ActiveMod { id: id.to_owned(), name: name.to_owned() },
+            None => ActiveMod { id: m.clone(), name: m },
+        })
+        .collect())
+}
+
+/// `info.sii`, next to each `game.sii`: what the save menu shows.
+#[derive(Debug, Clone, FromStruct)]
+#[sii(struct_name = "save_container")]
+pub struct SaveContainer {
+    /// Name given in game; empty for most automatic saves.
+    pub name: String,
+    /// In-game minutes since the profile was started.
+    pub time: u32,
+    /// Unix time the save was written.
+    pub file_time: u64,
+    pub version: u32,
+    #[sii(with = "dependencies")]
+    pub dependencies: Vec<Dependency>,
+    #[sii(optional)]
+    pub info_money_account: Option<i64>,
+    #[sii(optional)]
+    pub info_players_experience: Option<u32>,
+    #[sii(optional)]
+    pub info_visited_cities: Option<u32>,
+    #[sii(optional)]
+    pub info_explored_ratio: Option<f32>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DependencyKind {
+    Dlc,
+    Mod,
+    Other(String),
+}
+
+/// A DLC or mod the save needs, from `"<kind>|<id>|<display name>"`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    pub id: String,
+    pub name: String,
+}
+
+fn dependencies(value: &Struct) -> Result<Vec<Dependency>> {
+    value
+        .get_opt_as::<Vec<String>>("dependencies")?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|d| {
+            let mut parts = d.splitn(3, '|');
+            let (Some(kind), Some(id)) = (parts.next(), parts.next()) else {
+                return Err(anyhow!("cannot parse dependency '{d}'"));
+            };
+            let kind = match kind {
+                "dlc" => DependencyKind::Dlc,
+                "mod" => DependencyKind::Mod,
+                k => DependencyKind::Other(k.to_owned()),
+            };
+
+            Ok(Dependency {
+                kind,
+                id: id.to_owned(),
+                name: parts.next().unwrap_or(id).to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Reads the first `T::STRUCT_NAME` block of a small sii file, in any format.
+pub fn load_single<T: FromStruct>(path: &Path) -> Result<T> {
+    let name = T::STRUCT_NAME.ok_or_else(|| anyhow!("load_single needs a struct name"))?;
+    let blocks = read_structs_from_path(path)?;
+    let block = blocks
+        .iter()
+        .find(|b| b.struct_name == name)
+        .ok_or_else(|| anyhow!("no {name} in {}", path.display()))?;
+    T::from_struct(block, None)
+}
+
+impl UserProfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        load_single(path)
+    }
+}
+
+impl SaveContainer {
+    pub fn load(path: &Path) -> Result<Self> {
+        load_single(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::sii::text::Parser;
+
+    use super::{ActiveMod, DependencyKind, SaveContainer, UserProfile};
+
+    #[test]
+    fn parses_text_profile_and_info() -> Result<()> {
+        let profile = br#"SiiNunit
+{
+user_profile : _nameless.1c2.3d40 {
+ face: 3
+ brand: scania
+ map_path: "/map/europe.mbd"
+ logo: "logo_12"
+ company_name: "Trucks R Us"
+ male: true
+ cached_experience: 4200
+ cached_distance: 12000
+ version: 8
+ active_mods: 2
+ active_mods[0]: "promods|ProMods|Europe"
+ active_mods[1]: "trailers|Trailer Pack"
+ profile_name: "Jo"
+ creation_time: 1600000000
+ save_time: 1700000000
+}
+}
+"#;
+        let p = UserProfile::try_from(Parser::new_from_reader(&profile[..])?.next().unwrap()?)?;
+        assert_eq!(p.profile_name, "Jo");
+        assert_eq!(p.brand, "scania");
+        assert!(p.cached_discovery.is_empty());
+        assert_eq!(
+            p.active_mods,
+            vec![
+                ActiveMod { id: "promods".to_owned(), name: "ProMods|Europe".to_owned() },
+                ActiveMod { id: "trailers".to_owned(), name: "Trailer Pack".to_owned() },
+            ]
+        );
+
+        let info = br#"SiiNunit
+{
+save_container : _nameless.2b4.e8a0.6f10 {
+ name: "Before the ferry"
+ time: 81234
+ file_time: 1700000100
+ version: 8
+ dependencies: 2
+ dependencies[0]: "dlc|eut2_east|Going East!"
+ dependencies[1]: "mod|promods"
+}
+}
+"#;
+        let s = SaveContainer::try_from(Parser::new_from_reader(&info[..])?.next().unwrap()?)?;
+        assert_eq!(s.name, "Before the ferry");
+        assert_eq!(s.dependencies[0].kind, DependencyKind::Dlc);
+        assert_eq!(s.dependencies[0].name, "Going East!");
+        assert_eq!(s.dependencies[1].name, "promods");
+        assert_eq!(s.info_money_account, None);
+
+        // Elements out of order, or more or fewer than declared.
+        for bad in [
+            "dependencies: 2\n dependencies[1]: a\n dependencies[0]: b",
+            "dependencies: 2\n dependencies[0]: a",
+            "dependencies: 1\n dependencies[]: a\n dependencies[]: b",
+        ] {
+            let sii = format!("SiiNunit\n{{\nsave_container : .info {{\n {}\n}}\n}}\n", bad);
+            assert!(Parser::new_from_reader(sii.as_bytes())?.next().unwrap().is_err(), "{bad}");
+        }
+        Ok(())
+    }
+}
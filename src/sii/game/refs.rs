@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+use crate::sii::value::{Struct, Value, ID};
+
+use super::GameSave;
+
+/// One place a block is referenced from: a field of another block, and the
+/// position within that field if it is an array.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    pub from: ID,
+    pub field: String,
+    pub index: Option<usize>,
+}
+
+/// A reference to an ID that has no block in the save.
+///
+/// Some ID fields point at def data rather than at other save blocks (e.g.
+/// `cargo.*` in job offers), so not every dangling reference is corruption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReference {
+    pub reference: Reference,
+    pub target: ID,
+}
+
+/// Every non-null ID held by `block`, with where it was found.
+fn outgoing(block: &Struct) -> impl Iterator<Item = (Reference, &ID)> {
+    block.fields.iter().flat_map(move |(field, value)| {
+        let ids: Vec<(Option<usize>, &ID)> = match value {
+            Value::ID(id) => vec![(None, id)],
+            Value::IDArray(ids) => ids.iter().enumerate().map(|(i, id)| (Some(i), id)).collect(),
+            _ => vec![],
+        };
+
+        ids.into_iter().filter(|(_, id)| !id.is_null()).map(move |(index, id)| {
+            let reference = Reference {
+                from: block.id.clone(),
+                field: field.clone(),
+                index,
+            };
+            (reference, id)
+        })
+    })
+}
+
+/// Maps each referenced ID to the places it is referenced from.
+pub(super) fn index_references(blocks: &HashMap<ID, Struct>) -> HashMap<ID, Vec<Reference>> {
+    let mut referrers = HashMap::new();
+    for block in blocks.values() {
+        index_block(&mut referrers, block);
+    }
+
+    referrers
+}
+
+pub(super) fn index_block(referrers: &mut HashMap<ID, Vec<Reference>>, block: &Struct) {
+    for (reference, target) in outgoing(block) {
+        referrers.entry(target.clone()).or_default().push(reference);
+    }
+}
+
+/// Removes everything `index_block` added for `block`.
+pub(super) fn unindex_block(referrers: &mut HashMap<ID, Vec<Reference>>, block: &Struct) {
+    for (_, target) in outgoing(block) {
+        if let Some(refs) = referrers.get_mut(target) {
+            refs.retain(|r| r.from != block.id);
+            if refs.is_empty() {
+                referrers.remove(target);
+            }
+        }
+    }
+}
+
+impl GameSave {
+    /// Every place `id` is referenced from, in no particular order.
+    pub fn references_to(&self, id: &ID) -> &[Reference] {
+        self.referrers.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Follows the single reference in `field` of `block`.  Null references
+    /// are `None`; references to missing blocks are an error.
+    pub fn resolve(&self, block: &Struct, field: &str) -> Result<Option<&Struct>> {
+        match block.get_as::<Option<ID>>(field)? {
+            None => Ok(None),
+            Some(id) => self.referenced_block(block, field, &id).map(Some),
+        }
+    }
+
+    /// Follows every reference in the array `field` of `block`, skipping null
+    /// entries (e.g. empty garage slots).
+    pub fn resolve_array(&self, block: &Struct, field: &str) -> Result<Vec<&Struct>> {
+        block
+            .get_as::<Vec<Option<ID>>>(field)?
+            .iter()
+            .flatten()
+            .map(|id| self.referenced_block(block, field, id))
+            .collect()
+    }
+
+    fn referenced_block(&self, block: &Struct, field: &str, id: &ID) -> Result<&Struct> {
+        match self.get_block_by_id(id) {
+            Some(b) => Ok(b),
+            None => bail!(
+                "dangling reference to {id:?} in {} {:?}.{field}",
+                block.struct_name,
+                block.id
+            ),
+        }
+    }
+
+    /// Every reference to an ID with no block in the save.
+    pub fn dangling_references(&self) -> Vec<DanglingReference> {
+        let mut out: Vec<_> = self
+            .referrers
+            .iter()
+            .filter(|(target, _)| !self.blocks.contains_key(target))
+            .flat_map(|(target, refs)| {
+                refs.iter().map(|reference| DanglingReference {
+                    reference: reference.clone(),
+                    target: target.clone(),
+                })
+            })
+            .collect();
+        out.sort_by(|a, b| {
+            (&a.target, &a.reference.field, a.reference.index)
+                .cmp(&(&b.target, &b.reference.field, b.reference.index))
+        });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::{
+        sii::{
+            game::GameSave,
+            value::{Value, ID},
+        },
+        test_util::nameless,
+    };
+
+    use super::Reference;
+
+    #[test]
+    fn follows_and_indexes_references() -> Result<()> {
+        let save: GameSave = [
+            nameless(
+                1,
+                "player",
+                vec![
+                    ("assigned_truck", Value::ID(ID::Nameless(2))),
+                    ("assigned_trailer", Value::ID(ID::Named(vec![]))),
+                    ("trucks", Value::IDArray(vec![ID::Nameless(2), ID::Named(vec![]), ID::Nameless(9)])),
+                ],
+            ),
+            nameless(
+                2,
+                "vehicle",
+                vec![
+                    ("accessories", Value::IDArray(vec![ID::Nameless(3)])),
+                    ("engine_wear", Value::Single(0.25)),
+                ],
+            ),
+            nameless(3, "vehicle_accessory", vec![]),
+        ]
+        .into_iter()
+        .collect();
+
+        let player = save.single_block_named("player").unwrap();
+        let truck = save.resolve(player, "assigned_truck")?.unwrap();
+        assert_eq!(truck.get_as::<f32>("engine_wear")?, 0.25);
+        assert!(save.resolve(player, "assigned_trailer")?.is_none());
+        assert_eq!(save.resolve_array(truck, "accessories")?[0].id, ID::Nameless(3));
+        assert!(save.resolve_array(player, "trucks").is_err());
+
+        let mut refs = save.references_to(&ID::Nameless(2)).to_vec();
+        refs.sort_by_key(|r| r.field.clone());
+        assert_eq!(
+            refs,
+            vec![
+                Reference { from: ID::Nameless(1), field: "assigned_truck".to_owned(), index: None },
+                Reference { from: ID::Nameless(1), field: "trucks".to_owned(), index: Some(0) },
+            ]
+        );
+        assert!(save.references_to(&ID::Nameless(1)).is_empty());
+
+        let dangling = save.dangling_references();
+        assert_eq!(dangling.len(), 1);
+        assert_eq!(dangling[0].target, ID::Nameless(9));
+        assert_eq!(dangling[0].reference.index, Some(2));
+        Ok(())
+    }
+}
@@ -2,3 +2,5 @@ pub mod game;
 pub mod binary;
 pub mod text;
 pub mod value;
+pub mod diff;
+pub mod format;
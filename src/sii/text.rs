@@ -69,7 +69,7 @@ macro_rules! expect_char {
     ($next:expr, $p:literal) => {
         match $next {
             $p => {}
-            x => bail!("expected '{}' but found '{}'", x as char, $p),
+            x => bail!("expected '{}' but found '{}'", $p as char, x as char),
         }
     };
 }
@@ -82,12 +82,16 @@ pub enum Token {
     // enough for the achievements.sii file (which does not model any signed
     // numbers).
     Integer(u64),
+    // Def files do have a few negative numbers.
+    NegativeInteger(i64),
     Float(f32),
     Boolean(bool),
     LeftBrace,
     RightBrace,
     Colon,
     LeftRightBracket,
+    // field[3]: an array element at an explicit index.
+    Index(usize),
 }
 
 pub struct Lexer<I>(Peekable<I>)
@@ -102,7 +106,9 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
 
 impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
     fn next_inner(&mut self) -> Result<Token> {
-        self.skip_whitespace()?;
+        if self.skip_whitespace()? {
+            return self.read_path();
+        }
 
         match peek!(self.0) {
             b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' => {
@@ -120,6 +126,14 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
                 next!(self.0);
                 Ok(Token::Colon)
             }
+            b'-' => {
+                next!(self.0);
+                match self.read_identifier_or_number()? {
+                    Token::Integer(n) => Ok(Token::NegativeInteger(-i64::try_from(n)?)),
+                    Token::Float(f) => Ok(Token::Float(-f)),
+                    t => bail!("unexpected '-' before {:?}", t),
+                }
+            }
             b'[' => self.read_left_right_bracket(),
             b'"' => self.read_quoted_string(),
             0xEF => {
@@ -156,10 +170,25 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
         }
     }
 
+    // Bare paths such as data_path: /def/vehicle/truck.sii; the leading '/'
+    // has been consumed by skip_whitespace.
+    fn read_path(&mut self) -> Result<Token> {
+        let rest = take_string!(
+            self.0,
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'/'
+        );
+        Ok(Token::Identifier(format!("/{}", rest)))
+    }
+
     fn read_left_right_bracket(&mut self) -> Result<Token> {
         expect_char!(next!(self.0), b'[');
+        let index = take_string!(self.0, b'0'..=b'9');
         expect_char!(next!(self.0), b']');
-        Ok(Token::LeftRightBracket)
+        if index.is_empty() {
+            Ok(Token::LeftRightBracket)
+        } else {
+            Ok(Token::Index(usize::from_str(&index)?))
+        }
     }
 
     fn read_quoted_string(&mut self) -> Result<Token> {
@@ -195,10 +224,26 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
         Ok(Token::QuotedString(s))
     }
 
-    fn skip_whitespace(&mut self) -> Result<()> {
+    // Returns true if it stopped after a '/' that doesn't start a comment, so
+    // the '/' begins a bare path; only one byte of lookahead is available.
+    fn skip_whitespace(&mut self) -> Result<bool> {
         loop {
             match peek!(self.0) {
                 b'#' => self.skip_comment()?,
+                b'/' => {
+                    next!(self.0);
+                    match peek!(self.0) {
+                        b'/' => {
+                            next!(self.0);
+                            self.skip_comment()?
+                        }
+                        b'*' => {
+                            next!(self.0);
+                            self.skip_block_comment()?
+                        }
+                        _ => return Ok(true),
+                    }
+                }
                 b' ' | b'\t' | b'\r' | b'\n' => {
                     next!(self.0);
                 }
@@ -206,7 +251,7 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
             }
         }
 
-        Ok(())
+        Ok(false)
     }
 
     fn skip_utf8_bom(&mut self) -> Result<()> {
@@ -216,8 +261,7 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
         Ok(())
     }
 
-    // Technically I think the format supports // single-line comments and
-    // /* */ multiline comments, but achievements.sii doesn't use them.
+    // Skips the rest of a # or // comment.
     fn skip_comment(&mut self) -> Result<()> {
         let mut last = false;
         while !last {
@@ -229,6 +273,17 @@ impl<I: Iterator<Item = std::io::Result<u8>>> Lexer<I> {
 
         Ok(())
     }
+
+    // Skips to the end of a /* */ comment; the opening /* has been consumed.
+    fn skip_block_comment(&mut self) -> Result<()> {
+        let mut star = false;
+        loop {
+            match next!(self.0) {
+                b'/' if star => return Ok(()),
+                c => star = c == b'*',
+            }
+        }
+    }
 }
 
 impl<I: Iterator<Item = std::io::Result<u8>>> Iterator for Lexer<I> {
@@ -268,6 +323,9 @@ macro_rules! match_token {
 // TODO: make pub, make the new() handle creating a lexer from file
 pub struct Parser<L: Iterator<Item = Result<Token>>> {
     lexer: Peekable<L>,
+    // Included .sui files are bare lists of structs without the SiiNunit { }
+    // wrapper.
+    fragment: bool,
 }
 
 impl<L: Iterator<Item = Result<Token>>> Parser<L> {
@@ -277,12 +335,23 @@ impl<L: Iterator<Item = Result<Token>>> Parser<L> {
 
         Ok(Self {
             lexer: lexer.peekable(),
+            fragment: false,
         })
     }
 
+    /// Parses an included file (usually `.sui`), which has no `SiiNunit`
+    /// wrapper.
+    pub fn new_fragment(lexer: L) -> Self {
+        Self {
+            lexer: lexer.peekable(),
+            fragment: true,
+        }
+    }
+
     fn next_inner(&mut self) -> Result<Struct> {
         match self.lexer.peek() {
-            Some(Ok(Token::RightBrace)) => eof!(),
+            Some(Ok(Token::RightBrace)) if !self.fragment => eof!(),
+            None if self.fragment => eof!(),
             Some(Ok(Token::Identifier(_))) => self.read_struct(),
             Some(Ok(t)) => Err(anyhow!("unexpected {:?}", t)),
             Some(Err(e)) => Err(anyhow!("error in tokenizer: {e}")),
@@ -310,13 +379,19 @@ impl<L: Iterator<Item = Result<Token>>> Parser<L> {
                 }
                 _ => {
                     let field_name = match_token!(next!(self.lexer), Identifier);
-                    // field[]: value
-                    let is_array = match peek!(self.lexer) {
+                    // field[]: value, or field[i]: value with the elements in
+                    // order
+                    let (is_array, index) = match peek!(self.lexer) {
                         Token::LeftRightBracket => {
                             next!(self.lexer);
-                            true
+                            (true, None)
+                        }
+                        Token::Index(i) => {
+                            let i = *i;
+                            next!(self.lexer);
+                            (true, Some(i))
                         }
-                        _ => false,
+                        _ => (false, None),
                     };
                     expect_token!(next!(self.lexer), Token::Colon);
                     let field_value = match next!(self.lexer) {
@@ -329,6 +404,7 @@ impl<L: Iterator<Item = Result<Token>>> Parser<L> {
                         Token::Identifier(i) => Value::String(i),
                         Token::QuotedString(s) => Value::String(s),
                         Token::Integer(i) => Value::UInt64(i),
+                        Token::NegativeInteger(i) => Value::Int64(i),
                         Token::Float(f) => Value::Single(f),
                         Token::Boolean(b) => Value::ByteBool(b),
                         t => bail!("unexpected {:?}", t),
@@ -339,10 +415,13 @@ impl<L: Iterator<Item = Result<Token>>> Parser<L> {
                             arrays.insert(field_name.clone(), Vec::new());
                         }
 
-                        arrays
+                        let array = arrays
                             .get_mut(&field_name)
-                            .expect("inserted if it didn't exist")
-                            .push(field_value);
+                            .expect("inserted if it didn't exist");
+                        if let Some(i) = index.filter(|i| *i != array.len()) {
+                            bail!("expected {}[{}] but found {}[{}]", field_name, array.len(), field_name, i);
+                        }
+                        array.push(field_value);
                     } else {
                         fields.insert(field_name, field_value);
                     }
@@ -351,6 +430,12 @@ impl<L: Iterator<Item = Result<Token>>> Parser<L> {
         }
 
         for (name, values) in arrays {
+            // The elements are preceded by the array's length, field: n.
+            if let Some(Value::UInt64(n)) = fields.get(&name) {
+                if *n as usize != values.len() {
+                    bail!("{} has {} elements but declares {}", name, values.len(), n);
+                }
+            }
             let array_value = Value::try_from(values)?;
             fields.insert(name, array_value);
         }
@@ -398,7 +483,7 @@ mod tests {
     #[test]
     fn test_parse_achievements_sii() -> Result<()> {
         let core_scs_path = PathBuf::from("test_symlinks/game_path").join("core.scs");
-        let mut core = Archive::load_from_path(core_scs_path.to_str().expect("illegal filename"))?;
+        let core = Archive::load_from_path(core_scs_path.to_str().expect("illegal filename"))?;
         let reader = core.open_entry(ACHIEVEMENTS_SII_HASH)?;
         let mut parser = sii::text::Parser::new_from_reader(reader)?;
 
@@ -418,4 +503,29 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn parses_fragments_with_comments() -> Result<()> {
+        let sui = b"// berlin.sui
+city_data: city.berlin
+{
+	city_name: \"Berlin\" /* shown on the map */
+	time_zone: -60
+	offset: -1.5
+	data_path: /def/vehicle/truck/scania.r/data.sii // unquoted
+}
+";
+        let structs = sii::format::read_fragment_structs(sui)?;
+        assert_eq!(structs.len(), 1);
+        let city = &structs[0];
+        assert_eq!(city.id, ID::try_from("city.berlin")?);
+        assert_eq!(get_value_as!(city, "city_name", String)?, "Berlin");
+        assert_eq!(*get_value_as!(city, "time_zone", Int64)?, -60);
+        assert_eq!(*get_value_as!(city, "offset", Single)?, -1.5);
+        assert_eq!(
+            get_value_as!(city, "data_path", String)?,
+            "/def/vehicle/truck/scania.r/data.sii"
+        );
+        Ok(())
+    }
 }
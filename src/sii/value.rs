@@ -1,7 +1,7 @@
-use std::{collections::HashMap, fmt::Debug, io::Read};
+use std::{collections::HashMap, fmt::Debug, io::{Read, Write}};
 
 use anyhow::{anyhow, bail, Result};
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
 pub trait ReadFrom
 where
@@ -27,6 +27,28 @@ read_from!(u64, reader, Ok(reader.read_u64::<LittleEndian>()?));
 read_from!(i64, reader, Ok(reader.read_i64::<LittleEndian>()?));
 read_from!(f32, reader, Ok(reader.read_f32::<LittleEndian>()?));
 
+/// The inverse of `ReadFrom`, for writing binary sii files.
+pub trait WriteTo {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()>;
+}
+
+macro_rules! write_to {
+    ($t:ty, $self:ident, $w:ident, $($expr:tt)*) => {
+        impl WriteTo for $t {
+            fn write_to<W: Write>(&$self, $w: &mut W) -> Result<()> {
+                $($expr)*
+            }
+        }
+    };
+}
+
+write_to!(u16, self, writer, Ok(writer.write_u16::<LittleEndian>(*self)?));
+write_to!(i32, self, writer, Ok(writer.write_i32::<LittleEndian>(*self)?));
+write_to!(u32, self, writer, Ok(writer.write_u32::<LittleEndian>(*self)?));
+write_to!(u64, self, writer, Ok(writer.write_u64::<LittleEndian>(*self)?));
+write_to!(i64, self, writer, Ok(writer.write_i64::<LittleEndian>(*self)?));
+write_to!(f32, self, writer, Ok(writer.write_f32::<LittleEndian>(*self)?));
+
 macro_rules! def_vec {
     ($name:ident, $($t:ty),+) => {
         pub type $name = ($($t),+);
@@ -47,6 +69,21 @@ def_vec!(Vec4s, f32, f32, f32, f32);
 def_vec!(Vec8s, f32, f32, f32, f32, f32, f32, f32, f32);
 def_vec!(Vec3i, i32, i32, i32);
 
+macro_rules! write_tuple {
+    ($name:ident, $($idx:tt),+) => {
+        write_to!($name, self, writer, {
+            $(self.$idx.write_to(writer)?;)+
+            Ok(())
+        });
+    };
+}
+
+write_tuple!(Vec2s, 0, 1);
+write_tuple!(Vec3s, 0, 1, 2);
+write_tuple!(Vec4s, 0, 1, 2, 3);
+write_tuple!(Vec8s, 0, 1, 2, 3, 4, 5, 6, 7);
+write_tuple!(Vec3i, 0, 1, 2);
+
 #[derive(Debug, Clone)]
 pub struct OrdinalStringTable(HashMap<u32, String>);
 
@@ -54,6 +91,10 @@ impl OrdinalStringTable {
     pub fn get(&self, ordinal: u32) -> Option<&String> {
         self.0.get(&ordinal)
     }
+
+    pub fn ordinal_of(&self, s: &str) -> Option<u32> {
+        self.0.iter().find(|(_, v)| *v == s).map(|(k, _)| *k)
+    }
 }
 
 impl ReadFrom for OrdinalStringTable {
@@ -71,6 +112,21 @@ impl ReadFrom for OrdinalStringTable {
     }
 }
 
+impl WriteTo for OrdinalStringTable {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut entries: Vec<_> = self.0.iter().collect();
+        entries.sort();
+
+        (entries.len() as u32).write_to(writer)?;
+        for (ordinal, string) in entries {
+            ordinal.write_to(writer)?;
+            string.write_to(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub enum ID {
     Nameless(u64),
@@ -78,6 +134,17 @@ pub enum ID {
 }
 
 impl ID {
+    /// Whether this is a null reference: an empty ID in the binary format, or
+    /// the literal `null` in the text format.
+    pub fn is_null(&self) -> bool {
+        match self {
+            Self::Named(parts) => {
+                parts.is_empty() || (parts.len() == 1 && EncodedString(parts[0]).to_string() == "null")
+            }
+            Self::Nameless(_) => false,
+        }
+    }
+
     pub fn string_part(&self, index: isize) -> Option<String> {
         match self {
             Self::Named(parts) => {
@@ -108,12 +175,18 @@ impl Ord for ID {
 impl ToString for ID {
     fn to_string(&self) -> String {
         match self {
+            // 16 bit groups in hex, most significant first, without the
+            // leading zero groups: _nameless.1b0.f8b3.2730
             Self::Nameless(id) => {
-                let bytes = id.to_le_bytes();
-                format!(
-                    "_nameless.{:x}{:02x}.{:x}{:02x}.{:x}{:02x}.{:x}{:02x}",
-                    bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]
-                )
+                let mut groups = (0..4)
+                    .rev()
+                    .map(|g| (id >> (g * 16)) & 0xFFFF)
+                    .skip_while(|g| *g == 0);
+                let mut s = format!("_nameless.{:x}", groups.next().unwrap_or(0));
+                for group in groups {
+                    s.push_str(&format!(".{:04x}", group));
+                }
+                s
             }
             Self::Named(parts) => {
                 parts
@@ -130,8 +203,18 @@ impl TryFrom<&str> for ID {
     type Error = anyhow::Error;
 
     fn try_from(idstr: &str) -> Result<Self> {
-        if idstr.starts_with("_nameless") {
-            bail!("parsing nameless IDs is not supported yet");
+        if let Some(groups) = idstr.strip_prefix("_nameless.") {
+            let groups = groups.split('.').collect::<Vec<_>>();
+            if groups.len() > 4 {
+                bail!("too many parts in nameless ID '{}'", idstr);
+            }
+            let mut id = 0;
+            for group in groups {
+                let group = u16::from_str_radix(group, 16)
+                    .map_err(|e| anyhow!("cannot parse nameless ID '{}': {}", idstr, e))?;
+                id = id << 16 | group as u64;
+            }
+            return Ok(ID::Nameless(id));
         }
 
         let pieces = idstr
@@ -181,7 +264,29 @@ impl ReadFrom for ID {
     }
 }
 
-#[derive(Clone, Copy)]
+impl WriteTo for ID {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        match self {
+            Self::Nameless(id) => {
+                writer.write_u8(0xFF)?;
+                id.write_to(writer)
+            }
+            Self::Named(parts) => {
+                if parts.len() >= 0xFF {
+                    bail!("too many parts in ID {self:?}");
+                }
+
+                writer.write_u8(parts.len() as u8)?;
+                for p in parts {
+                    p.write_to(writer)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct EncodedString(u64);
 
 impl EncodedString {
@@ -194,6 +299,8 @@ read_from!(
     Ok(EncodedString(u64::read_from(reader)?))
 );
 
+write_to!(EncodedString, self, writer, self.0.write_to(writer));
+
 impl ToString for EncodedString {
     fn to_string(&self) -> String {
         let mut res = String::new();
@@ -263,6 +370,21 @@ where
     }
 }
 
+impl<T> WriteTo for Vec<T>
+where
+    T: WriteTo,
+{
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let len = u32::try_from(self.len()).map_err(|_| anyhow!("array too long"))?;
+        len.write_to(writer)?;
+        for v in self {
+            v.write_to(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl ReadFrom for String {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
         let len = u32::read_from(reader)?;
@@ -274,6 +396,15 @@ impl ReadFrom for String {
     }
 }
 
+impl WriteTo for String {
+    fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let len = u32::try_from(self.len()).map_err(|_| anyhow!("string too long"))?;
+        len.write_to(writer)?;
+        writer.write_all(self.as_bytes())?;
+        Ok(())
+    }
+}
+
 impl ReadFrom for bool {
     fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
         if reader.read_u8()? == 0 {
@@ -284,8 +415,10 @@ impl ReadFrom for bool {
     }
 }
 
+write_to!(bool, self, writer, Ok(writer.write_u8(*self as u8)?));
+
 /// See https://github.com/TheLazyTomcat/SII_Decrypt/blob/master/Documents/Binary%20SII%20-%20Types.txt
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     String(String),
     StringArray(Vec<String>),
@@ -364,6 +497,95 @@ impl Value {
         }
     }
 
+    /// Whether this value can be stored in a field of binary `value_type`.
+    pub fn matches_type(&self, value_type: u32) -> bool {
+        matches!(
+            (value_type, self),
+            (0x01, Self::String(_))
+                | (0x02, Self::StringArray(_))
+                | (0x03, Self::EncodedString(_))
+                | (0x04, Self::EncodedStringArray(_))
+                | (0x05, Self::Single(_))
+                | (0x06, Self::SingleArray(_))
+                | (0x07, Self::Vec2s(_))
+                | (0x09, Self::Vec3s(_))
+                | (0x0A, Self::Vec3sArray(_))
+                | (0x11, Self::Vec3i(_))
+                | (0x12, Self::Vec3iArray(_))
+                | (0x17, Self::Vec4s(_))
+                | (0x18, Self::Vec4sArray(_))
+                | (0x19, Self::Vec8s(_))
+                | (0x1A, Self::Vec8sArray(_))
+                | (0x25, Self::Int32(_))
+                | (0x26, Self::Int32Array(_))
+                | (0x27 | 0x2F, Self::UInt32(_))
+                | (0x28, Self::UInt32Array(_))
+                | (0x2B, Self::UInt16(_))
+                | (0x2C, Self::UInt16Array(_))
+                | (0x31, Self::Int64(_))
+                | (0x32, Self::Int64Array(_))
+                | (0x33, Self::UInt64(_))
+                | (0x34, Self::UInt64Array(_))
+                | (0x35, Self::ByteBool(_))
+                | (0x36, Self::ByteBoolArray(_))
+                | (0x37, Self::OrdinalString(_))
+                | (0x39 | 0x3B | 0x3D, Self::ID(_))
+                | (0x3A | 0x3C, Self::IDArray(_))
+        )
+    }
+
+    /// Writes the value in the binary format.  `value_type` is only checked,
+    /// since every type with the same `Value` variant has the same encoding.
+    pub fn write_to<W: Write>(
+        &self,
+        writer: &mut W,
+        value_type: u32,
+        ordinal_table: Option<&OrdinalStringTable>,
+    ) -> Result<()> {
+        if !self.matches_type(value_type) {
+            bail!("cannot write {} as value type {value_type:X}", self.type_name());
+        }
+
+        match self {
+            Self::String(v) => v.write_to(writer),
+            Self::StringArray(v) => v.write_to(writer),
+            Self::EncodedString(v) => v.write_to(writer),
+            Self::EncodedStringArray(v) => v.write_to(writer),
+            Self::Single(v) => v.write_to(writer),
+            Self::SingleArray(v) => v.write_to(writer),
+            Self::Vec2s(v) => v.write_to(writer),
+            Self::Vec3s(v) => v.write_to(writer),
+            Self::Vec3sArray(v) => v.write_to(writer),
+            Self::Vec3i(v) => v.write_to(writer),
+            Self::Vec3iArray(v) => v.write_to(writer),
+            Self::Vec4s(v) => v.write_to(writer),
+            Self::Vec4sArray(v) => v.write_to(writer),
+            Self::Vec8s(v) => v.write_to(writer),
+            Self::Vec8sArray(v) => v.write_to(writer),
+            Self::Int32(v) => v.write_to(writer),
+            Self::Int32Array(v) => v.write_to(writer),
+            Self::UInt32(v) => v.write_to(writer),
+            Self::UInt32Array(v) => v.write_to(writer),
+            Self::UInt16(v) => v.write_to(writer),
+            Self::UInt16Array(v) => v.write_to(writer),
+            Self::Int64(v) => v.write_to(writer),
+            Self::Int64Array(v) => v.write_to(writer),
+            Self::UInt64(v) => v.write_to(writer),
+            Self::UInt64Array(v) => v.write_to(writer),
+            Self::ByteBool(v) => v.write_to(writer),
+            Self::ByteBoolArray(v) => v.write_to(writer),
+            Self::OrdinalString(s) => {
+                let table = ordinal_table.ok_or_else(|| anyhow!("missing ordinal table"))?;
+                table
+                    .ordinal_of(s)
+                    .ok_or_else(|| anyhow!("'{s}' is not in the ordinal table"))?
+                    .write_to(writer)
+            }
+            Self::ID(v) => v.write_to(writer),
+            Self::IDArray(v) => v.write_to(writer),
+        }
+    }
+
     fn read_ordinal_string<R: Read>(
         reader: &mut R,
         table: Option<&OrdinalStringTable>,
@@ -420,13 +642,250 @@ impl TryFrom<Vec<Value>> for Value {
     }
 }
 
-#[derive(Debug)]
+impl Value {
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "String",
+            Self::StringArray(_) => "StringArray",
+            Self::EncodedString(_) => "EncodedString",
+            Self::EncodedStringArray(_) => "EncodedStringArray",
+            Self::Single(_) => "Single",
+            Self::SingleArray(_) => "SingleArray",
+            Self::Vec2s(_) => "Vec2s",
+            Self::Vec3s(_) => "Vec3s",
+            Self::Vec3sArray(_) => "Vec3sArray",
+            Self::Vec3i(_) => "Vec3i",
+            Self::Vec3iArray(_) => "Vec3iArray",
+            Self::Vec4s(_) => "Vec4s",
+            Self::Vec4sArray(_) => "Vec4sArray",
+            Self::Vec8s(_) => "Vec8s",
+            Self::Vec8sArray(_) => "Vec8sArray",
+            Self::Int32(_) => "Int32",
+            Self::Int32Array(_) => "Int32Array",
+            Self::UInt32(_) => "UInt32",
+            Self::UInt32Array(_) => "UInt32Array",
+            Self::UInt16(_) => "UInt16",
+            Self::UInt16Array(_) => "UInt16Array",
+            Self::Int64(_) => "Int64",
+            Self::Int64Array(_) => "Int64Array",
+            Self::UInt64(_) => "UInt64",
+            Self::UInt64Array(_) => "UInt64Array",
+            Self::ByteBool(_) => "ByteBool",
+            Self::ByteBoolArray(_) => "ByteBoolArray",
+            Self::OrdinalString(_) => "OrdinalString",
+            Self::ID(_) => "ID",
+            Self::IDArray(_) => "IDArray",
+        }
+    }
+
+    pub(crate) fn as_integer(&self) -> Option<i128> {
+        match self {
+            Self::Int32(v) => Some(*v as i128),
+            Self::UInt32(v) => Some(*v as i128),
+            Self::UInt16(v) => Some(*v as i128),
+            Self::Int64(v) => Some(*v as i128),
+            Self::UInt64(v) => Some(*v as i128),
+            _ => None,
+        }
+    }
+
+    fn as_integers(&self) -> Option<Vec<i128>> {
+        match self {
+            Self::Int32Array(a) => Some(a.iter().map(|v| *v as i128).collect()),
+            Self::UInt32Array(a) => Some(a.iter().map(|v| *v as i128).collect()),
+            Self::UInt16Array(a) => Some(a.iter().map(|v| *v as i128).collect()),
+            Self::Int64Array(a) => Some(a.iter().map(|v| *v as i128).collect()),
+            Self::UInt64Array(a) => Some(a.iter().map(|v| *v as i128).collect()),
+            _ => None,
+        }
+    }
+
+    // In the text format an array's elements (`field[0]: ...` or
+    // `field[]: ...`) follow its length (`field: 2`).  The parser replaces the
+    // length with the elements, so an empty array is left as just the length.
+    fn is_empty_array_length(&self) -> bool {
+        matches!(self, Self::UInt64(0))
+    }
+}
+
+/// Conversion from a field `Value` into a plain Rust type.  Integers convert
+/// between widths as long as the value fits, since the binary format is not
+/// consistent about which integer type a field uses and the text format parses
+/// every integer as a `UInt64`.
+pub trait FromValue
+where
+    Self: Sized,
+{
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+fn expected<T>(what: &str, value: &Value) -> Result<T> {
+    bail!("expected {} but found {}", what, value.type_name())
+}
+
+macro_rules! from_integer_value {
+    ($($t:ty),+) => {$(
+        impl FromValue for $t {
+            fn from_value(value: &Value) -> Result<Self> {
+                match value.as_integer() {
+                    Some(n) => <$t>::try_from(n)
+                        .map_err(|_| anyhow!("{} does not fit in {}", n, stringify!($t))),
+                    None => expected(stringify!($t), value),
+                }
+            }
+        }
+
+        impl FromValue for Vec<$t> {
+            fn from_value(value: &Value) -> Result<Self> {
+                if value.is_empty_array_length() {
+                    return Ok(Vec::new());
+                }
+
+                match value.as_integers() {
+                    Some(a) => a
+                        .into_iter()
+                        .map(|n| {
+                            <$t>::try_from(n)
+                                .map_err(|_| anyhow!("{} does not fit in {}", n, stringify!($t)))
+                        })
+                        .collect(),
+                    None => expected(concat!("array of ", stringify!($t)), value),
+                }
+            }
+        }
+    )+};
+}
+
+from_integer_value!(u16, u32, u64, i32, i64);
+
+impl FromValue for f32 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Single(v) => Ok(*v),
+            v => match v.as_integer() {
+                Some(n) => Ok(n as f32),
+                None => expected("f32", v),
+            },
+        }
+    }
+}
+
+impl FromValue for Vec<f32> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::SingleArray(a) => Ok(a.clone()),
+            v if v.is_empty_array_length() => Ok(Vec::new()),
+            v => expected("array of f32", v),
+        }
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::ByteBool(b) => Ok(*b),
+            v => expected("bool", v),
+        }
+    }
+}
+
+impl FromValue for Vec<bool> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::ByteBoolArray(a) => Ok(a.clone()),
+            v if v.is_empty_array_length() => Ok(Vec::new()),
+            v => expected("array of bool", v),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(s) | Value::OrdinalString(s) => Ok(s.clone()),
+            Value::EncodedString(s) => Ok(s.to_string()),
+            v => expected("string", v),
+        }
+    }
+}
+
+impl FromValue for Vec<String> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::StringArray(a) => Ok(a.clone()),
+            Value::EncodedStringArray(a) => Ok(a.iter().map(|s| s.to_string()).collect()),
+            v if v.is_empty_array_length() => Ok(Vec::new()),
+            v => expected("array of strings", v),
+        }
+    }
+}
+
+// The text format has no separate ID type; see text::Parser.
+impl FromValue for ID {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::ID(id) => Ok(id.clone()),
+            Value::String(s) => ID::try_from(s.as_str()),
+            v => expected("ID", v),
+        }
+    }
+}
+
+/// A nullable reference: null IDs become `None`.
+impl FromValue for Option<ID> {
+    fn from_value(value: &Value) -> Result<Self> {
+        ID::from_value(value).map(|id| if id.is_null() { None } else { Some(id) })
+    }
+}
+
+impl FromValue for Vec<ID> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::IDArray(a) => Ok(a.clone()),
+            Value::StringArray(a) => a.iter().map(|s| ID::try_from(s.as_str())).collect(),
+            v if v.is_empty_array_length() => Ok(Vec::new()),
+            v => expected("array of IDs", v),
+        }
+    }
+}
+
+impl FromValue for Vec<Option<ID>> {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(Vec::<ID>::from_value(value)?
+            .into_iter()
+            .map(|id| if id.is_null() { None } else { Some(id) })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
     pub id: ID,
     pub struct_name: String,
     pub fields: HashMap<String, Value>,
 }
 
+impl Struct {
+    /// Gets a field converted to `T`; see `FromValue`.
+    pub fn get_as<T: FromValue>(&self, field: &str) -> Result<T> {
+        match self.fields.get(field) {
+            None => bail!("missing field {} in {} {:?}", field, self.struct_name, self.id),
+            Some(v) => T::from_value(v).map_err(|e| {
+                anyhow!("mismatched type for {} in {} {:?}: {e}", field, self.struct_name, self.id)
+            }),
+        }
+    }
+
+    /// Like `get_as`, but a missing field is `None` rather than an error.
+    pub fn get_opt_as<T: FromValue>(&self, field: &str) -> Result<Option<T>> {
+        if self.fields.contains_key(field) {
+            self.get_as(field).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! get_value_as {
     ($b:ident, $fname:expr, $variant:ident) => {
@@ -451,7 +910,9 @@ macro_rules! take_value_as {
 
 #[cfg(test)]
 mod tests {
-    use super::{EncodedString, ID};
+    use std::collections::HashMap;
+
+    use super::{EncodedString, Struct, Value, ID};
 
     #[test]
     fn round_trip_encoded_string() {
@@ -467,4 +928,49 @@ mod tests {
         let id = ID::try_from(company).unwrap();
         assert_eq!(id.to_string(), company);
     }
+
+    #[test]
+    fn round_trip_nameless_id() {
+        for nameless in ["_nameless.1b0.f8b3.2730", "_nameless.2.0000.00a0.7e10", "_nameless.0"] {
+            let id = ID::try_from(nameless).unwrap();
+            assert!(matches!(id, ID::Nameless(_)));
+            assert_eq!(id.to_string(), nameless);
+        }
+        assert_eq!(ID::try_from("_nameless.1.0002").unwrap(), ID::Nameless(0x1_0002));
+        assert!(ID::try_from("_nameless.12345").is_err());
+        assert!(ID::try_from("_nameless.1.2.3.4.5").is_err());
+    }
+
+    #[test]
+    fn get_as_converts_compatible_values() {
+        let s = Struct {
+            id: ID::try_from("bank.test").unwrap(),
+            struct_name: "bank".to_owned(),
+            fields: HashMap::from([
+                ("money".to_owned(), Value::Int64(1500)),
+                ("debt".to_owned(), Value::Int64(-1)),
+                ("count".to_owned(), Value::UInt64(0)),
+                ("loans".to_owned(), Value::IDArray(vec![ID::Named(vec![]), ID::Nameless(7)])),
+                ("city".to_owned(), Value::EncodedString(EncodedString::try_from("berlin").unwrap())),
+                ("company".to_owned(), Value::String("company.volatile.tradeaux.berlin".to_owned())),
+            ]),
+        };
+
+        assert_eq!(s.get_as::<u32>("money").unwrap(), 1500);
+        assert!(s.get_as::<u32>("debt").is_err());
+        assert!(s.get_as::<String>("money").is_err());
+        assert!(s.get_as::<u32>("missing").is_err());
+        assert_eq!(s.get_opt_as::<u32>("missing").unwrap(), None);
+        assert_eq!(s.get_as::<Vec<ID>>("count").unwrap(), vec![]);
+        assert_eq!(
+            s.get_as::<Vec<Option<ID>>>("loans").unwrap(),
+            vec![None, Some(ID::Nameless(7))]
+        );
+        assert_eq!(s.get_as::<String>("city").unwrap(), "berlin");
+        assert_eq!(
+            s.get_as::<ID>("company").unwrap().to_string(),
+            "company.volatile.tradeaux.berlin"
+        );
+        assert!(ID::try_from("null").unwrap().is_null());
+    }
 }
@@ -0,0 +1,49 @@
+//! Fixtures shared by the unit tests.
+
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::Result;
+
+use crate::{
+    scs::writer::ArchiveWriter,
+    sii::value::{EncodedString, Struct, Value, ID},
+};
+
+pub(crate) fn block(id: ID, struct_name: &str, fields: Vec<(&str, Value)>) -> Struct {
+    Struct {
+        id,
+        struct_name: struct_name.to_owned(),
+        fields: fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+    }
+}
+
+pub(crate) fn nameless(id: u64, struct_name: &str, fields: Vec<(&str, Value)>) -> Struct {
+    block(ID::Nameless(id), struct_name, fields)
+}
+
+/// A token such as a city name, the way the binary format stores it.
+pub(crate) fn token(s: &str) -> Value {
+    Value::EncodedString(EncodedString::try_from(s).unwrap())
+}
+
+/// A path in the system temp dir unique to this test run; `name` keeps tests
+/// running in parallel apart.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("siirs_{}_{}", name, std::process::id()))
+}
+
+/// Creates the `temp_path` directory for `name`.
+pub(crate) fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = temp_path(name);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Writes an uncompressed archive holding `files` to `path`.
+pub(crate) fn write_archive(path: &Path, files: &[(&str, Vec<u8>)]) -> Result<()> {
+    let mut archive = ArchiveWriter::new(false);
+    for (name, data) in files {
+        archive.add_file(name, data.clone())?;
+    }
+    archive.write(&mut fs::File::create(path)?)
+}